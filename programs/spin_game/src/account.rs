@@ -1,4 +1,5 @@
 use anchor_lang::prelude::*;
+use std::mem::size_of;
 
 use crate::constants::*;
 use crate::errors::*;
@@ -7,12 +8,223 @@ use crate::errors::*;
 #[account]
 #[derive(Default)]
 pub struct Pool {
+    // every wheel account is derived from this pool, which is derived from wheel_id
+    pub wheel_id: u64,
     pub superadmin: Pubkey,
-    pub dev_wallet: Pubkey,
-    pub burn_wallet: Pubkey,
+
+    // entry fee split; burn shares are burned at spin time, the rest accrue
+    // below and are paid out of the vaults by sweep_fees
+    pub fee_recipients: [FeeRecipient; MAX_FEE_RECIPIENTS],
+    pub fee_count: u8,
+    // one entry per (wallet, mint) with fees outstanding, removed once swept
+    pub fee_accruals: Vec<FeeAccrual>,
+
+    // RANDOMNESS_SOURCE_* used for new spins, oracle signs the requests of RANDOMNESS_SOURCE_ORACLE spins
+    pub randomness_source: u8,
+    pub oracle: Pubkey,
+
+    // sum every item table must reach before it can be spun
+    pub ratio_total: u64,
+
+    // seconds a settled prize can be claimed for, 0 for no limit
+    pub claim_window: i64,
+
+    // per mint: prizes settled but not yet claimed or expired, fees not yet swept
+    // and what unsettled spins reserved. One entry per mint owed, so the account
+    // grows (see Pool::space) instead of running out of slots.
+    pub liabilities: Vec<Liability>,
+    // share of each vault balance withdrawals must leave on top of liabilities
+    pub reserve_bps: u16,
+
+    // what a spin costs in each accepted mint
+    pub payment_options: [PaymentOption; MAX_PAYMENT_MINTS],
+    pub payment_count: u8,
+
+    // limits on the Pyth prices behind PRICE_MODE_USD_CENTS options
+    pub max_price_age_slots: u64,
+    pub max_price_conf_bps: u16,
+
+    // spin_many batches of at least bulk_discount_min draws get bulk_discount_bps off, 0 to disable
+    pub bulk_discount_min: u8,
+    pub bulk_discount_bps: u16,
+
+    // keys allowed to sign vouchers for this wheel
+    pub voucher_issuers: [Pubkey; MAX_VOUCHER_ISSUERS],
+    pub voucher_issuer_count: u8,
+
+    // free_spin_allowance spins every free_spin_period seconds per user, 0 to disable
+    pub free_spin_allowance: u8,
+    pub free_spin_period: i64,
+    // free spins draw from the FREE_ITEM_LIST_SEED table instead of the paid one
+    pub free_spin_table: bool,
+}
+
+impl Pool {
+    pub fn fee_schedule(&self) -> &[FeeRecipient] {
+        &self.fee_recipients[..self.fee_count as usize]
+    }
+
+    // shares must leave something for the vault
+    pub fn set_fee_schedule(&mut self, recipients: &[FeeRecipient]) -> Result<()> {
+        require!(recipients.len() <= MAX_FEE_RECIPIENTS, SpinError::IncorrectFee);
+        let total_bps: u64 = recipients.iter().map(|r| r.bps as u64).sum();
+        require!(total_bps < BPS_DENOMINATOR, SpinError::IncorrectFee);
+        require!(recipients.iter().all(|r| r.burn || r.wallet != Pubkey::default()), SpinError::IncorrectFee);
+
+        self.fee_recipients = Default::default();
+        self.fee_recipients[..recipients.len()].copy_from_slice(recipients);
+        self.fee_count = recipients.len() as u8;
+
+        Ok(())
+    }
+
+    pub fn voucher_issuers(&self) -> &[Pubkey] {
+        &self.voucher_issuers[..self.voucher_issuer_count as usize]
+    }
+
+    pub fn set_voucher_issuers(&mut self, issuers: &[Pubkey]) -> Result<()> {
+        require!(issuers.len() <= MAX_VOUCHER_ISSUERS, SpinError::InvalidVoucher);
+        require!(issuers.iter().all(|issuer| *issuer != Pubkey::default()), SpinError::InvalidVoucher);
+
+        self.voucher_issuers = Default::default();
+        self.voucher_issuers[..issuers.len()].copy_from_slice(issuers);
+        self.voucher_issuer_count = issuers.len() as u8;
+
+        Ok(())
+    }
+
+    pub fn payment_option(&self, mint: &Pubkey) -> Option<PaymentOption> {
+        self.payment_options[..self.payment_count as usize]
+            .iter()
+            .find(|option| option.mint == *mint)
+            .copied()
+    }
+
+    pub fn payment_option_mut(&mut self, mint: &Pubkey) -> Option<&mut PaymentOption> {
+        self.payment_options[..self.payment_count as usize]
+            .iter_mut()
+            .find(|option| option.mint == *mint)
+    }
+
+    // adds or reprices `mint`; a zero price stops accepting it
+    pub fn set_payment_option(&mut self, mint: Pubkey, price: u64) -> Result<()> {
+        let count = self.payment_count as usize;
+        let found = self.payment_options[..count].iter().position(|option| option.mint == mint);
+        match (found, price) {
+            (Some(i), 0) => {
+                self.payment_options[i] = self.payment_options[count - 1];
+                self.payment_options[count - 1] = PaymentOption::default();
+                self.payment_count -= 1;
+            }
+            (Some(i), _) => self.payment_options[i].price = price,
+            (None, 0) => {}
+            (None, _) => {
+                require!(count < MAX_PAYMENT_MINTS, SpinError::TooManyPaymentMints);
+                self.payment_options[count] = PaymentOption { mint, price, ..Default::default() };
+                self.payment_count += 1;
+            }
+        }
+        Ok(())
+    }
+
+    // also counted as a liability until swept
+    pub fn accrue_fee(&mut self, wallet: Pubkey, mint: Pubkey, amount: u64) -> Result<()> {
+        if amount == 0 {
+            return Ok(());
+        }
+        match self.fee_accruals.iter_mut().find(|a| a.wallet == wallet && a.mint == mint) {
+            Some(accrual) => {
+                accrual.amount = accrual.amount.checked_add(amount).ok_or(SpinError::RewardAmountOverflow)?;
+            }
+            None => {
+                require!(self.fee_accruals.len() < MAX_FEE_ACCRUALS, SpinError::TooManyFeeAccruals);
+                self.fee_accruals.push(FeeAccrual { wallet, mint, amount });
+            }
+        }
+
+        self.add_liability(mint, amount)
+    }
+
+    pub fn liability(&self, mint: &Pubkey) -> u64 {
+        self.liabilities.iter().find(|l| l.mint == *mint).map_or(0, |l| l.amount)
+    }
+
+    pub fn add_liability(&mut self, mint: Pubkey, amount: u64) -> Result<()> {
+        if amount == 0 {
+            return Ok(());
+        }
+        match self.liabilities.iter_mut().find(|l| l.mint == mint) {
+            Some(liability) => {
+                liability.amount = liability.amount.checked_add(amount).ok_or(SpinError::RewardAmountOverflow)?;
+            }
+            None => self.liabilities.push(Liability { mint, amount }),
+        }
+
+        Ok(())
+    }
+
+    pub fn release_liability(&mut self, mint: &Pubkey, amount: u64) {
+        if let Some(i) = self.liabilities.iter().position(|l| l.mint == *mint) {
+            let liability = &mut self.liabilities[i];
+            liability.amount = liability.amount.saturating_sub(amount);
+            if liability.amount == 0 {
+                self.liabilities.swap_remove(i);
+            }
+        }
+    }
+
+    // bytes the account needs as it stands; adding a liability mint can grow it
+    pub fn space(&self) -> usize {
+        8 + self.try_to_vec().map_or(0, |data| data.len())
+    }
+
+    // what the house may take out of a vault holding `balance` of `mint`
+    pub fn withdrawable(&self, mint: &Pubkey, balance: u64) -> u64 {
+        let reserve = (balance as u128 * self.reserve_bps as u128 / BPS_DENOMINATOR as u128) as u64;
+        balance.saturating_sub(self.liability(mint)).saturating_sub(reserve)
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
+pub struct FeeRecipient {
+    pub wallet: Pubkey,
+    pub bps: u16,
+    // free-form, e.g. "treasury" or "partner", zero padded
+    pub label: [u8; FEE_LABEL_LEN],
+    // the share is burned (lamports sent to the incinerator) instead of paid to wallet
+    pub burn: bool,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
+pub struct FeeAccrual {
+    pub wallet: Pubkey,
+    // NATIVE_SOL_MINT for lamports in the SOL vault
+    pub mint: Pubkey,
+    pub amount: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
+pub struct PaymentOption {
+    // NATIVE_SOL_MINT for lamports
+    pub mint: Pubkey,
+    // read according to price_mode
     pub price: u64,
-    pub dev_fee: u64,
-    pub burn_fee: u64,
+    pub price_mode: u8,
+    // Pyth price account of the mint in USD, PRICE_MODE_USD_CENTS only
+    pub price_feed: Pubkey,
+    // base units destroyed by burn fee recipients
+    pub total_burned: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
+pub struct Liability {
+    // NATIVE_SOL_MINT for the SOL vault
+    pub mint: Pubkey,
+    pub amount: u64,
+}
+
+impl Liability {
+    pub const SIZE: usize = 32 + 8;
 }
 
 
@@ -25,7 +237,73 @@ pub struct UserState {
     // user
     pub user: Pubkey,
 
+    // pending accounts are seeded by round, see RequestSpin
     pub round_num: u32,
+
+    // rounds whose pending account is still open
+    pub pending_rounds: [u32; MAX_PENDING_ROUNDS],
+    pub pending_count: u8,
+
+    // free spins taken in the period that started at free_period_start
+    pub free_period_start: i64,
+    pub free_spins_used: u8,
+    pub last_free_spin_at: i64,
+}
+
+impl UserState {
+    // starts the next round for `user`, whose pending account the caller opens
+    pub fn begin_round(&mut self, user: Pubkey) -> Result<u32> {
+        if self.is_initialized == 0 {
+            self.is_initialized = 1;
+            self.user = user;
+            self.round_num = 1;
+        } else {
+            require!(self.user == user, SpinError::IncorrectUserState);
+            self.round_num += 1;
+        }
+        self.push_pending_round(self.round_num)?;
+
+        Ok(self.round_num)
+    }
+
+    // uses one of the pool's free spins, starting a new period once the last one is over
+    pub fn take_free_spin(&mut self, pool: &Pool, now: i64) -> Result<()> {
+        require!(pool.free_spin_allowance > 0 && pool.free_spin_period > 0, SpinError::NoFreeSpin);
+
+        if now - self.free_period_start >= pool.free_spin_period {
+            self.free_period_start = now;
+            self.free_spins_used = 0;
+        }
+        require!(self.free_spins_used < pool.free_spin_allowance, SpinError::NoFreeSpin);
+        self.free_spins_used += 1;
+        self.last_free_spin_at = now;
+
+        Ok(())
+    }
+
+    pub fn push_pending_round(&mut self, round_num: u32) -> Result<()> {
+        require!((self.pending_count as usize) < MAX_PENDING_ROUNDS, SpinError::TooManyPendingRounds);
+
+        self.pending_rounds[self.pending_count as usize] = round_num;
+        self.pending_count += 1;
+
+        Ok(())
+    }
+
+    pub fn remove_pending_round(&mut self, round_num: u32) {
+        let count = self.pending_count as usize;
+        if let Some(i) = self.pending_rounds[..count].iter().position(|r| *r == round_num) {
+            self.pending_rounds.copy_within(i + 1..count, i);
+            self.pending_count -= 1;
+        }
+    }
+}
+
+// an open pending account, as returned by the pending_rounds view
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default)]
+pub struct PendingRound {
+    pub round_num: u32,
+    pub address: Pubkey,
 }
 
 #[account]
@@ -33,27 +311,118 @@ pub struct UserState {
 pub struct UserPendingClaimState {
     // user
     pub user: Pubkey,
-    pub is_claimed: u8,
+    pub pool: Pubkey,
     pub round_num: u32,
 
-    pub pending_mint_list: [Pubkey; REWARD_TOKEN_COUNT_PER_ITEM],
-    pub pending_amount_list: [u64; REWARD_TOKEN_COUNT_PER_ITEM],
-    pub is_claimed_list: [bool; REWARD_TOKEN_COUNT_PER_ITEM],
-    pub is_sol: bool,
-    pub sol_amount: u64,
-    pub count: u8,
+    // commit-reveal: hash(secret) committed in request_spin, revealed in settle_spin
+    pub commitment: [u8; 32],
+    pub reveal_slot: u64,
+    // slot whose hash seeded the draws, reveal_slot or the next produced one; set by settle_spin
+    pub entropy_slot: u64,
+    // see fn_settle_deadline; past it the spin can only be forfeited
+    pub settle_deadline: u64,
+    pub pay_mint: Pubkey,
+    // total paid for all spin_count draws, 0 for free spins
+    pub pay_amount: u64,
+    // SpinItemList the draws come from, and the items of it that took part,
+    // fixed before any entropy exists
+    pub item_table: Pubkey,
+    pub affordable_mask: [u8; 32],
+    pub is_settled: bool,
+    pub randomness_source: u8,
+    // pool.oracle when the spin was requested, the only key settle_spin accepts
+    pub oracle: Pubkey,
+
+    // unix time after which reclaim_expired may release the prizes, 0 for never
+    pub expires_at: i64,
+
+    // draws bought with this commitment, 1 unless bought through spin_many
+    pub spin_count: u8,
+    // worst case the draws can owe per mint, held in pool.liabilities from the
+    // request until settle_spin swaps it for the prizes actually won
+    pub reserved: Vec<Liability>,
+    // one entry per draw that paid something, filled by settle_spin
+    pub prizes: Vec<PendingPrize>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
+pub struct PendingPrize {
+    // NATIVE_SOL_MINT for lamports
+    pub mint: Pubkey,
+    // base units, fixed at settle time
+    pub amount: u64,
+    pub is_claimed: bool,
+    // where the prize came from, so reclaim_expired can put it back
+    pub token_type: u8,
+    pub item_index: u8,
+    pub decimals: u8,
+    // NFT prizes transfer exactly one token, from a verified `collection` unless default
+    pub collection: Pubkey,
+}
+
+impl PendingPrize {
+    pub const SIZE: usize = 32 + 8 + 1 + 1 + 1 + 1 + 32;
 }
 
 impl UserPendingClaimState {
-    pub fn add_item(&mut self, pending_mint: Pubkey, amount: u64) -> Result<()> {
-        require!(self.count <= REWARD_TOKEN_COUNT_PER_ITEM as u8, SpinError::CountOverflowAddItem);
+    // fields before the reserved and prizes lists
+    const HEADER_SIZE: usize = 32 + 32 + 4 + 32 + 8 + 8 + 8 + 32 + 8 + 32 + 32 + 1 + 1 + 32 + 8 + 1;
 
-        self.pending_mint_list[self.count as usize] = pending_mint;
-        self.pending_amount_list[self.count as usize] = amount;
-        self.count += 1;
+    pub fn space(spin_count: u8, reserved_mints: usize) -> usize {
+        8 + Self::HEADER_SIZE + 4 + reserved_mints * Liability::SIZE + 4 + spin_count as usize * PendingPrize::SIZE
+    }
+
+    pub fn add_prize(&mut self, prize: PendingPrize) -> Result<()> {
+        require!(self.prizes.len() < self.spin_count as usize, SpinError::CountOverflowAddItem);
+
+        self.prizes.push(prize);
 
         Ok(())
     }
+
+    pub fn is_expired(&self, now: i64) -> bool {
+        self.expires_at != 0 && now > self.expires_at
+    }
+
+    // unsettled and past the slot it could have been settled by
+    pub fn missed_settle_deadline(&self, slot: u64) -> bool {
+        !self.is_settled && self.settle_deadline != 0 && slot > self.settle_deadline
+    }
+
+    // (mint, amount) still owed, NATIVE_SOL_MINT for lamports
+    pub fn unclaimed(&self) -> Vec<(Pubkey, u64)> {
+        self.prizes.iter().filter(|p| !p.is_claimed).map(|p| (p.mint, p.amount)).collect()
+    }
+
+    pub fn has_unclaimed(&self) -> bool {
+        self.prizes.iter().any(|p| !p.is_claimed)
+    }
+
+    // marks every SOL prize claimed, returning the lamports they were owed
+    pub fn take_unclaimed_sol(&mut self) -> u64 {
+        let mut lamports = 0;
+        for prize in self.prizes.iter_mut().filter(|p| !p.is_claimed && p.token_type == TOKEN_TYPE_SOL) {
+            prize.is_claimed = true;
+            lamports += prize.amount;
+        }
+        lamports
+    }
+
+    // first unclaimed token prize paying `mint`
+    pub fn next_unclaimed(&self, mint: &Pubkey) -> Option<usize> {
+        self.prizes.iter().position(|p| !p.is_claimed && p.token_type != TOKEN_TYPE_SOL && p.mint == *mint)
+    }
+}
+
+// exists once the voucher (issuer, nonce) has been redeemed on the pool
+#[account]
+#[derive(Default)]
+pub struct UsedVoucher {
+    pub pool: Pubkey,
+    pub issuer: Pubkey,
+    pub nonce: u64,
+    pub user: Pubkey,
+    pub redeemed_at: i64,
 }
 
 #[account]
@@ -87,73 +456,178 @@ impl AdminInfo {
 }
 
 
-// space : 32 * 10 + 1
+// space : 32 * 10 + 1 + 10
 #[zero_copy]
 #[derive(Default, AnchorSerialize, AnchorDeserialize)]
 pub struct ItemRewardMints {
     pub item_mint_list: [Pubkey; REWARD_TOKEN_COUNT_PER_ITEM],
     pub count: u8,
+    // read from each mint account when the item is configured
+    pub decimals_list: [u8; REWARD_TOKEN_COUNT_PER_ITEM],
 }
 
-// space : 5020 // old : 4975
-#[account(zero_copy)]
+// space : 331 + 1 + 4 + 8 + 4 + 32
+#[zero_copy]
 #[repr(packed)]
 #[repr(C)]
+#[derive(Default)]
+pub struct SpinItem {
+    pub reward_mints: ItemRewardMints,
+    pub token_type: u8,
+    pub ratio: u32,
+    // lamports for TOKEN_TYPE_SOL, whole tokens of the paid mint otherwise
+    pub amount: u64,
+    // prizes left to hand out, UNLIMITED_STOCK for no limit
+    pub stock: u32,
+    // TOKEN_TYPE_NFT only: required verified collection, default for any
+    pub collection: Pubkey,
+}
+
+impl SpinItem {
+    pub fn is_depleted(&self) -> bool {
+        // these hand out one mint slot per win, so they also end with their mint list
+        let uses_mint_slots = self.token_type == TOKEN_TYPE_MINT_LIST || self.token_type == TOKEN_TYPE_NFT;
+        self.stock == 0 || (uses_mint_slots && self.reward_mints.count == 0)
+    }
+
+    // amount paid per win in base units of the mint handed out next
+    pub fn prize_amount(&self) -> Result<u64> {
+        let reward_mints = self.reward_mints;
+        if self.token_type == TOKEN_TYPE_NFT {
+            Ok(1)
+        } else if self.token_type == TOKEN_TYPE_SOL || reward_mints.count == 0 {
+            Ok(self.amount)
+        } else {
+            self.amount_in(reward_mints.count as usize - 1)
+        }
+    }
 
+    // base units of reward_mints[index] one win pays
+    fn amount_in(&self, index: usize) -> Result<u64> {
+        if self.token_type == TOKEN_TYPE_NFT {
+            return Ok(1);
+        }
+        10_u64
+            .checked_pow(self.reward_mints.decimals_list[index] as u32)
+            .and_then(|unit| unit.checked_mul(self.amount))
+            .ok_or(error!(SpinError::RewardAmountOverflow))
+    }
+
+    // (mint, amount) of each of the next `wins` wins that pays something, the way
+    // settle_spin hands them out: mint-list and NFT items work down their list,
+    // the others pay the same every time
+    pub fn win_payouts(&self, wins: u8) -> Result<Vec<(Pubkey, u64)>> {
+        let stock = self.stock;
+        let wins = if stock == UNLIMITED_STOCK { wins as usize } else { stock.min(wins as u32) as usize };
+        let reward_mints = self.reward_mints;
+        let count = reward_mints.count as usize;
+
+        if self.token_type == TOKEN_TYPE_SOL {
+            Ok(vec![(NATIVE_SOL_MINT, self.amount); wins])
+        } else if self.token_type == TOKEN_TYPE_MINT_LIST || self.token_type == TOKEN_TYPE_NFT {
+            (1..=wins.min(count))
+                .map(|j| Ok((reward_mints.item_mint_list[count - j], self.amount_in(count - j)?)))
+                .collect()
+        } else if count > 0 {
+            Ok(vec![(reward_mints.item_mint_list[count - 1], self.amount_in(count - 1)?); wins])
+        } else {
+            Ok(vec![])
+        }
+    }
+
+    // in stock and payable out of the given free balances
+    pub fn is_affordable(&self, sol_balance: u64, token_balances: &[(Pubkey, u64)]) -> Result<bool> {
+        let amount = self.prize_amount()?;
+        let reward_mints = self.reward_mints;
+
+        if self.is_depleted() {
+            Ok(false)
+        } else if self.token_type == TOKEN_TYPE_SOL {
+            Ok(amount <= sol_balance)
+        } else if reward_mints.count == 0 {
+            Ok(true)
+        } else {
+            let mint = reward_mints.item_mint_list[(reward_mints.count - 1) as usize];
+            let (_, balance) = token_balances
+                .iter()
+                .find(|(vault_mint, _)| *vault_mint == mint)
+                .ok_or(SpinError::MissingRewardVault)?;
+            Ok(amount <= *balance)
+        }
+    }
+
+    pub fn take_stock(&mut self) {
+        if self.stock != UNLIMITED_STOCK {
+            self.stock -= 1;
+        }
+    }
+
+    pub fn restock(&mut self, amount: u32) -> Result<()> {
+        if self.stock != UNLIMITED_STOCK {
+            self.stock = self.stock.checked_add(amount).filter(|stock| *stock != UNLIMITED_STOCK).ok_or(SpinError::StockOverflow)?;
+        }
+        Ok(())
+    }
+}
+
+// Header of the item table. The account data is
+// discriminator (8) | SpinItemList | [SpinItem; capacity]
+// and resize_items grows or shrinks the trailing region with realloc.
+#[account(zero_copy)]
+#[derive(Default)]
 pub struct SpinItemList {
-    pub reward_mint_list: [ItemRewardMints; SPIN_ITEM_COUNT],   // 321 * 15
-    pub token_type_list: [u8; SPIN_ITEM_COUNT],   // 15
-    pub ratio_list: [u32; SPIN_ITEM_COUNT],  // 4 * 15
-    pub amount_list: [u64; SPIN_ITEM_COUNT],    // 8 * 15
-    pub last_spinindex: u8, // 1
-    pub count: u8, // 1
+    pub capacity: u8,
+    pub count: u8,
+    pub last_spinindex: u8,
 }
 
 impl ItemRewardMints {
-    pub fn add_reward_item(&mut self, reward_mint: Pubkey) {
+    pub fn add_reward_item(&mut self, reward_mint: Pubkey, decimals: u8) -> Result<()> {
+        require!((self.count as usize) < REWARD_TOKEN_COUNT_PER_ITEM, SpinError::CountOverflowAddItem);
+
         self.item_mint_list[self.count as usize] = reward_mint;
+        self.decimals_list[self.count as usize] = decimals;
         self.count += 1;
-    }
-}
 
-impl Default for SpinItemList {
-    #[inline]
-    fn default() -> SpinItemList {
-        SpinItemList {
-            reward_mint_list: [
-                ItemRewardMints {
-                    ..Default::default()
-                }; SPIN_ITEM_COUNT
-            ],
-            token_type_list: [0; SPIN_ITEM_COUNT],
-            ratio_list: [0; SPIN_ITEM_COUNT],
-            amount_list: [0; SPIN_ITEM_COUNT],
-            last_spinindex: 0,
-            count: 0,
-        }
+        Ok(())
     }
 }
 
 impl SpinItemList {
-    pub fn add_spinitem(&mut self, item_mint_list: ItemRewardMints, token_type: u8, ratio: u32, amount: u64,) -> Result<()> {
-        require!(self.count <= SPIN_ITEM_COUNT as u8, SpinError::CountOverflowAddItem);
+    pub fn space(capacity: u8) -> usize {
+        8 + size_of::<SpinItemList>() + capacity as usize * size_of::<SpinItem>()
+    }
 
-        self.reward_mint_list[self.count as usize] = item_mint_list;
-        self.token_type_list[self.count as usize] = token_type;
-        self.ratio_list[self.count as usize] = ratio;
-        self.amount_list[self.count as usize] = amount;
+    // splits raw account data into the header and its `capacity` items
+    pub fn from_data(data: &[u8]) -> (&SpinItemList, &[SpinItem]) {
+        let (header, rest) = data[8..].split_at(size_of::<SpinItemList>());
+        let header: &SpinItemList = bytemuck::from_bytes(header);
+        let len = (header.capacity as usize).min(rest.len() / size_of::<SpinItem>());
+        (header, bytemuck::cast_slice(&rest[..len * size_of::<SpinItem>()]))
+    }
+
+    pub fn from_data_mut(data: &mut [u8]) -> (&mut SpinItemList, &mut [SpinItem]) {
+        let (header, rest) = data[8..].split_at_mut(size_of::<SpinItemList>());
+        let header: &mut SpinItemList = bytemuck::from_bytes_mut(header);
+        let len = (header.capacity as usize).min(rest.len() / size_of::<SpinItem>());
+        (header, bytemuck::cast_slice_mut(&mut rest[..len * size_of::<SpinItem>()]))
+    }
+
+    pub fn add_spinitem(&mut self, items: &mut [SpinItem], item: SpinItem, ratio_total: u64) -> Result<()> {
+        require!((self.count as usize) < items.len(), SpinError::CountOverflowAddItem);
+        // adding only ever grows the sum, so an overshoot can't be fixed by later items
+        require!(self.ratio_sum(items) + item.ratio as u64 <= ratio_total, SpinError::InvalidRatioTable);
+
+        items[self.count as usize] = item;
         self.count += 1;
 
         Ok(())
     }
 
-    pub fn set_spinitem(&mut self, index: u8, item_mint_list: ItemRewardMints, token_type: u8, ratio: u32, amount: u64,) -> Result<()> {
-        require!(index < SPIN_ITEM_COUNT as u8, SpinError::IndexOverflowSetItem);
+    pub fn set_spinitem(&mut self, items: &mut [SpinItem], index: u8, item: SpinItem) -> Result<()> {
+        require!((index as usize) < items.len(), SpinError::IndexOverflowSetItem);
 
-        self.reward_mint_list[index as usize] = item_mint_list;
-        self.token_type_list[index as usize] = token_type;
-        self.ratio_list[index as usize] = ratio;
-        self.amount_list[index as usize] = amount;
+        items[index as usize] = item;
         if self.count <= index {
             self.count = index + 1;
         }
@@ -165,30 +639,135 @@ impl SpinItemList {
         self.count = 0;
     }
 
-    
-    pub fn get_spinresult(&mut self, rand: u32) {
-        let ctime = Clock::get().unwrap();
-        let c = ctime.unix_timestamp * rand as i64;
-        let mut r = (c % 101) as u32;
-        if r == 100 {
-            r = 99;
+    pub fn ratio_sum(&self, items: &[SpinItem]) -> u64 {
+        items[..self.count as usize].iter().map(|item| item.ratio as u64).sum()
+    }
+
+    pub fn odds(&self, items: &[SpinItem], ratio_total: u64) -> SpinOdds {
+        let ratio_sum = self.ratio_sum(items);
+        SpinOdds {
+            ratio_list: items[..self.count as usize].iter().map(|item| item.ratio).collect(),
+            ratio_sum,
+            ratio_total,
+            is_valid: ratio_sum == ratio_total,
         }
+    }
 
-        let r_pow = r * ((10 as u32).pow(3));
-        let mut start = 0;
-        let unaligned = std::ptr::addr_of!(self.ratio_list);
-        let temp_ratio_list = unsafe { std::ptr::read_unaligned(unaligned) };
-        for (pos, item) in temp_ratio_list.iter().enumerate() {
-            let end = start + item;
-            if r_pow >= start && r_pow < end {
-                self.last_spinindex = pos as u8;
-                return;
+    // Items a spin of `spin_count` draws may land on (bit i for item i), fixed when
+    // the spin is requested, and what it reserves for them. Starts from every in-stock
+    // item with a weight and drops the ones paying a mint the reservation would
+    // overdraw, until the free balances cover all of it.
+    pub fn affordable_mask(
+        &self,
+        items: &[SpinItem],
+        sol_balance: u64,
+        token_balances: &[(Pubkey, u64)],
+        spin_count: u8,
+    ) -> Result<([u8; 32], Vec<Liability>)> {
+        let items = &items[..self.count as usize];
+        let mut mask = [0u8; 32];
+        for (i, item) in items.iter().enumerate() {
+            if item.ratio > 0 && !item.is_depleted() {
+                mask[i / 8] |= 1 << (i % 8);
             }
-            start = end;
         }
+
+        loop {
+            let reservation = self.reservation(items, &mask, spin_count)?;
+            let mut overdrawn = Vec::new();
+            for liability in &reservation {
+                let free = if liability.mint == NATIVE_SOL_MINT {
+                    sol_balance
+                } else {
+                    token_balances
+                        .iter()
+                        .find(|(mint, _)| *mint == liability.mint)
+                        .map(|(_, balance)| *balance)
+                        .ok_or(SpinError::MissingRewardVault)?
+                };
+                if liability.amount > free {
+                    overdrawn.push(liability.mint);
+                }
+            }
+            if overdrawn.is_empty() {
+                return Ok((mask, reservation));
+            }
+
+            for (i, item) in items.iter().enumerate() {
+                if Self::in_mask(&mask, i) && item.win_payouts(spin_count)?.iter().any(|(mint, _)| overdrawn.contains(mint)) {
+                    mask[i / 8] &= !(1 << (i % 8));
+                }
+            }
+        }
+    }
+
+    // Most `spin_count` draws over the items in `mask` can owe per mint: never
+    // more than spin_count times the largest single win, nor more than every
+    // win those items have left between them.
+    pub fn reservation(&self, items: &[SpinItem], mask: &[u8; 32], spin_count: u8) -> Result<Vec<Liability>> {
+        let mut largest: Vec<Liability> = Vec::new();
+        let mut total: Vec<Liability> = Vec::new();
+        for (i, item) in items[..self.count as usize].iter().enumerate() {
+            if !Self::in_mask(mask, i) {
+                continue;
+            }
+            for (mint, amount) in item.win_payouts(spin_count)? {
+                match largest.iter().position(|l| l.mint == mint) {
+                    Some(at) => {
+                        largest[at].amount = largest[at].amount.max(amount);
+                        total[at].amount = total[at].amount.saturating_add(amount);
+                    }
+                    None => {
+                        largest.push(Liability { mint, amount });
+                        total.push(Liability { mint, amount });
+                    }
+                }
+            }
+        }
+
+        for (liability, total) in largest.iter_mut().zip(total) {
+            liability.amount = liability.amount.saturating_mul(spin_count as u64).min(total.amount);
+        }
+        largest.retain(|l| l.amount > 0);
+        Ok(largest)
+    }
+
+    pub fn in_mask(mask: &[u8; 32], index: usize) -> bool {
+        mask[index / 8] & (1 << (index % 8)) != 0
+    }
+
+    // current ratios of the items in `mask`, 0 for the rest
+    pub fn masked_weights(&self, items: &[SpinItem], mask: &[u8; 32]) -> Vec<u32> {
+        items[..self.count as usize]
+            .iter()
+            .enumerate()
+            .map(|(i, item)| if Self::in_mask(mask, i) { item.ratio } else { 0 })
+            .collect()
+    }
+
+    // SPL mints the next `spin_count` wins of each item can pay, one reward vault per entry
+    pub fn prize_mints(&self, items: &[SpinItem], spin_count: u8) -> Result<Vec<Pubkey>> {
+        let mut mints: Vec<Pubkey> = Vec::new();
+        for item in &items[..self.count as usize] {
+            for (mint, _) in item.win_payouts(spin_count)? {
+                if mint != NATIVE_SOL_MINT && !mints.contains(&mint) {
+                    mints.push(mint);
+                }
+            }
+        }
+        Ok(mints)
     }
 }
 
+// item i wins with probability ratio_list[i] / ratio_total while is_valid
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default)]
+pub struct SpinOdds {
+    pub ratio_list: Vec<u32>,
+    pub ratio_sum: u64,
+    pub ratio_total: u64,
+    pub is_valid: bool,
+}
+
 #[account]
 #[derive(Default)]
 pub struct LatestUsers {
@@ -222,8 +801,135 @@ impl LatestUsers {
         self.reward_mint[0] = reward_mint;
         self.reward_type[0] = reward_type;
 
-        self.count += 1;
+        // only the newest MAX_LATEST_USER_COUNT entries are kept
+        self.count = (self.count + 1).min(MAX_LATEST_USER_COUNT as u8);
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const USDC: Pubkey = Pubkey::new_from_array([1; 32]);
+
+    fn item(token_type: u8, ratio: u32, amount: u64, mints: &[Pubkey]) -> SpinItem {
+        let mut item = SpinItem { token_type, ratio, amount, stock: UNLIMITED_STOCK, ..Default::default() };
+        for mint in mints {
+            item.reward_mints.add_reward_item(*mint, 0).unwrap();
+        }
+        item
+    }
+
+    fn table(items: &[SpinItem]) -> SpinItemList {
+        SpinItemList { capacity: items.len() as u8, count: items.len() as u8, last_spinindex: 0 }
+    }
+
+    #[test]
+    fn reservation_covers_largest_win_per_draw() {
+        let items = [
+            item(TOKEN_TYPE_TOKEN, 50, 10, &[USDC]),
+            item(TOKEN_TYPE_TOKEN, 40, 30, &[USDC]),
+            item(TOKEN_TYPE_SOL, 10, 1_000, &[]),
+        ];
+        let (mask, reserved) = table(&items).affordable_mask(&items, 10_000, &[(USDC, 1_000)], 3).unwrap();
+        assert_eq!(mask[0], 0b111);
+        assert_eq!(
+            reserved.iter().map(|l| (l.mint, l.amount)).collect::<Vec<_>>(),
+            vec![(USDC, 90), (NATIVE_SOL_MINT, 3_000)]
+        );
+    }
+
+    #[test]
+    fn overdrawn_mint_leaves_the_draw() {
+        let items = [item(TOKEN_TYPE_TOKEN, 50, 10, &[USDC]), item(TOKEN_TYPE_TOKEN, 50, 30, &[USDC]), item(TOKEN_TYPE_SOL, 0, 1, &[])];
+        // 2 draws of the 30 prize don't fit in 50, so both USDC items go
+        let (mask, reserved) = table(&items).affordable_mask(&items, 0, &[(USDC, 50)], 2).unwrap();
+        assert_eq!(mask[0], 0);
+        assert!(reserved.is_empty());
+    }
+
+    #[test]
+    fn nft_reserves_each_mint_once() {
+        let nfts = [Pubkey::new_from_array([2; 32]), Pubkey::new_from_array([3; 32])];
+        let items = [item(TOKEN_TYPE_NFT, 10, 0, &nfts), item(TOKEN_TYPE_TOKEN, 90, 1, &[USDC])];
+        let balances = [(USDC, 100), (nfts[0], 1), (nfts[1], 1)];
+        let table = table(&items);
+
+        assert_eq!(table.prize_mints(&items, 1).unwrap(), vec![nfts[1], USDC]);
+        assert_eq!(table.prize_mints(&items, 5).unwrap(), vec![nfts[1], nfts[0], USDC]);
+
+        let (mask, reserved) = table.affordable_mask(&items, 0, &balances, 5).unwrap();
+        assert_eq!(mask[0], 0b11);
+        assert_eq!(
+            reserved.iter().map(|l| (l.mint, l.amount)).collect::<Vec<_>>(),
+            vec![(nfts[1], 1), (nfts[0], 1), (USDC, 5)]
+        );
+    }
+
+    #[test]
+    fn liabilities_grow_and_shrink_per_mint() {
+        let mut pool = Pool::default();
+        let empty = pool.space();
+        for i in 1..=40u8 {
+            pool.add_liability(Pubkey::new_from_array([i; 32]), 1).unwrap();
+        }
+        pool.add_liability(NATIVE_SOL_MINT, 5).unwrap();
+        assert_eq!(pool.space(), empty + 41 * Liability::SIZE);
+        assert_eq!(pool.liability(&NATIVE_SOL_MINT), 5);
+
+        pool.release_liability(&NATIVE_SOL_MINT, 5);
+        assert_eq!(pool.liability(&NATIVE_SOL_MINT), 0);
+        assert_eq!(pool.liabilities.len(), 40);
+    }
+
+    #[test]
+    fn fee_accruals_fit_every_recipient_and_mint() {
+        let mut pool = Pool::default();
+        for wallet in 0..MAX_FEE_RECIPIENTS as u8 {
+            for mint in 0..MAX_PAYMENT_MINTS as u8 {
+                pool.accrue_fee(Pubkey::new_from_array([wallet + 1; 32]), Pubkey::new_from_array([mint + 100; 32]), 1).unwrap();
+            }
+        }
+        assert_eq!(pool.fee_accruals.len(), MAX_FEE_ACCRUALS);
+
+        // same (wallet, mint) adds up, a new pair past the limit doesn't fit
+        pool.accrue_fee(Pubkey::new_from_array([1; 32]), Pubkey::new_from_array([100; 32]), 2).unwrap();
+        assert_eq!(pool.fee_accruals[0].amount, 3);
+        assert!(pool.accrue_fee(Pubkey::new_from_array([50; 32]), NATIVE_SOL_MINT, 1).is_err());
+    }
+
+    fn prize(mint: Pubkey, amount: u64, token_type: u8) -> PendingPrize {
+        PendingPrize { mint, amount, token_type, ..Default::default() }
+    }
+
+    #[test]
+    fn sol_only_claim_pays_everything() {
+        let mut pending = UserPendingClaimState {
+            is_settled: true,
+            spin_count: 2,
+            prizes: vec![prize(NATIVE_SOL_MINT, 300, TOKEN_TYPE_SOL), prize(NATIVE_SOL_MINT, 200, TOKEN_TYPE_SOL)],
+            ..Default::default()
+        };
+        // no token prize for the SPL claim path to find
+        assert_eq!(pending.next_unclaimed(&NATIVE_SOL_MINT), None);
+
+        assert_eq!(pending.take_unclaimed_sol(), 500);
+        assert!(!pending.has_unclaimed());
+        assert_eq!(pending.take_unclaimed_sol(), 0);
+    }
+
+    #[test]
+    fn sol_claim_leaves_token_prizes() {
+        let mut pending = UserPendingClaimState {
+            is_settled: true,
+            spin_count: 2,
+            prizes: vec![prize(USDC, 7, TOKEN_TYPE_TOKEN), prize(NATIVE_SOL_MINT, 300, TOKEN_TYPE_SOL)],
+            ..Default::default()
+        };
+        assert_eq!(pending.take_unclaimed_sol(), 300);
+        assert_eq!(pending.unclaimed(), vec![(USDC, 7)]);
+        assert_eq!(pending.next_unclaimed(&USDC), Some(0));
+    }
+}
@@ -1,22 +1,78 @@
+use anchor_lang::prelude::*;
 
 pub const DEV_WALLET_KEY: &str = "74abUnzELqrKsJip68xVQdvduUUzQanjsdoN6jnRzqfp";
 
 pub const INITIALIZER_KEY: &str = "3ttYrBAp5D2sTG2gaBjg8EtrZecqBQSBuFRhsqHWPYxX";
-// pub const FRONK_MINT: &str = "5yxNbU8DgYJZNi3mPD9rs4XLh9ckXrhPjJ5VCujUWg5H"; //mainnet
-pub const FRONK_MINT: &str = "EBjBZHvnhCyQXFQJrjcu66PqBbhh6bHhjA5z7Cjyb5oD"; //devnet
 
 pub const ESCROW_PDA_SEED: &str = "sw_game_vault_auth";
 pub const USER_STATE_SEED: &[u8] = b"USER_STATE_SEED";
 pub const ADMIN_LIST_SEED: &[u8] = b"ADMIN_LIST_SEED";
 pub const VAULT_SEED: &[u8] = b"SOL_VAULT";
 pub const LAST_USERS_SEED: &str = "LAST_USERS_SEED";
+pub const ITEM_LIST_SEED: &[u8] = b"ITEM_LIST_SEED";
+// optional item table free spins draw from, see init_free_items
+pub const FREE_ITEM_LIST_SEED: &[u8] = b"FREE_ITEM_LIST_SEED";
+// per-mint reward vault: [REWARD_VAULT_SEED, pool, mint], authority is the pool
+pub const REWARD_VAULT_SEED: &[u8] = b"REWARD_VAULT_SEED";
+// redeemed voucher record: [VOUCHER_SEED, pool, issuer, nonce]
+pub const VOUCHER_SEED: &[u8] = b"VOUCHER_SEED";
 
 
+// initial item table capacity, see resize_items
 pub const SPIN_ITEM_COUNT: usize = 15;
+// SpinItem::token_type values
+pub const TOKEN_TYPE_TOKEN: u8 = 0;
+pub const TOKEN_TYPE_MINT_LIST: u8 = 1; // hands out one mint slot per win
+pub const TOKEN_TYPE_SOL: u8 = 2;
+pub const TOKEN_TYPE_NFT: u8 = 3; // one NFT from the pool's escrow per win
+
+// SpinItem::stock value for items that never run out
+pub const UNLIMITED_STOCK: u32 = u32::MAX;
+// stands for lamports wherever a mint is expected: payment options, liabilities.
+// Not a mint at all, so wrapped SOL stays an ordinary SPL payment option.
+pub const NATIVE_SOL_MINT: Pubkey = Pubkey::new_from_array([0; 32]);
 pub const REWARD_TOKEN_COUNT_PER_ITEM: usize = 10;
 pub const ADMIN_MAX_COUNT: usize = 15;
 pub const MAX_LATEST_USER_COUNT: usize = 10;
+// PaymentOption::price_mode values
+pub const PRICE_MODE_TOKEN: u8 = 0; // price in base units of the payment mint
+pub const PRICE_MODE_USD_CENTS: u8 = 1; // price in USD cents, converted through price_feed
+
+// pub const PYTH_PROGRAM_ID: Pubkey = solana_program::pubkey!("FsJ3A3u2vn5cTVofAjvy6y5kwABJAqYWpe4975bi2epH"); //mainnet
+pub const PYTH_PROGRAM_ID: Pubkey = solana_program::pubkey!("gSbePebfvPy7tRqimPoVecS2UsBvYv46ynrzWocc92s"); //devnet
+pub const DEFAULT_MAX_PRICE_AGE_SLOTS: u64 = 25;
+pub const DEFAULT_MAX_PRICE_CONF_BPS: u16 = 200;
+
+// fee schedule limits, see FeeRecipient
+pub const MAX_FEE_RECIPIENTS: usize = 8;
+pub const FEE_LABEL_LEN: usize = 16;
+// (recipient, mint) fee balances waiting for sweep_fees, room for every
+// recipient of a full schedule in every payment mint
+pub const MAX_FEE_ACCRUALS: usize = MAX_FEE_RECIPIENTS * MAX_PAYMENT_MINTS;
+// dev_wallet's share of every spin on a new wheel
+pub const DEFAULT_DEV_FEE_BPS: u16 = 300;
+
+// mints a wheel accepts for spins, native SOL included
+pub const MAX_PAYMENT_MINTS: usize = 8;
+pub const BPS_DENOMINATOR: u64 = 10_000;
+// unclaimed rounds tracked per user; request_spin fails until one is closed
+pub const MAX_PENDING_ROUNDS: usize = 32;
+// draws one spin_many call may buy, bounded by settle_spin's compute budget
+pub const MAX_BATCH_SPINS: u8 = 20;
+// keys whose signed vouchers the wheel honours
+pub const MAX_VOUCHER_ISSUERS: usize = 4;
 pub const MAX_REWARD_TOKEN_COUNT: usize = 150; // REWARD_TOKEN_COUNT_PER_ITEM * SPIN_ITEM_COUNT;
 
-pub const REWARD_TOKEN_DECIMAL: u8 = 5;
 pub const PERCENT_MULTIPLIER: u64 = 1000;
+// item ratios must sum to the pool's ratio_total; this default keeps ratio = percent * PERCENT_MULTIPLIER
+pub const DEFAULT_RATIO_TOTAL: u64 = 100 * PERCENT_MULTIPLIER;
+
+// slots between request_spin and the slot whose hash settles it
+pub const SPIN_REVEAL_DELAY_SLOTS: u64 = 2;
+// SlotHashes only keeps this many recent slots, a slot-hash spin must settle within them
+pub const SLOT_HASHES_WINDOW: u64 = 512;
+
+// randomness_source values stored on the pool
+pub const RANDOMNESS_SOURCE_SLOT_HASHES: u8 = 0;
+pub const RANDOMNESS_SOURCE_ORACLE: u8 = 1;
+pub const RANDOMNESS_SOURCE_SEEDED: u8 = 2; // test-rng builds only
@@ -28,4 +28,110 @@ pub enum SpinError {
 
     #[msg("Incorrect Fee")]
     IncorrectFee,
-}
\ No newline at end of file
+
+    #[msg("Spin Already Settled")]
+    SpinAlreadySettled,
+
+    #[msg("Secret Does Not Match Commitment")]
+    InvalidReveal,
+
+    #[msg("Reveal Slot Not Reached")]
+    RevealTooEarly,
+
+    #[msg("Slot Hash Unavailable")]
+    SlotHashUnavailable,
+
+    #[msg("Invalid Randomness Source")]
+    InvalidRandomnessSource,
+
+    #[msg("Missing Or Invalid Oracle Signature")]
+    InvalidOracleSignature,
+
+    #[msg("No Affordable Item")]
+    NoAffordableItem,
+
+    #[msg("Missing Reward Vault")]
+    MissingRewardVault,
+
+    #[msg("Invalid Reward Vault")]
+    InvalidRewardVault,
+
+    #[msg("Ratio Table Does Not Sum To Total")]
+    InvalidRatioTable,
+
+    #[msg("Capacity Below Item Count")]
+    CapacityBelowItemCount,
+
+    #[msg("Item Stock Overflow")]
+    StockOverflow,
+
+    #[msg("Invalid Nft Prize")]
+    InvalidNft,
+
+    #[msg("Nft Not In Verified Collection")]
+    InvalidNftCollection,
+
+    #[msg("Missing Reward Mint")]
+    MissingRewardMint,
+
+    #[msg("Reward Amount Overflow")]
+    RewardAmountOverflow,
+
+    #[msg("Too Many Pending Rounds")]
+    TooManyPendingRounds,
+
+    #[msg("Invalid Claim Window")]
+    InvalidClaimWindow,
+
+    #[msg("Claim Window Expired")]
+    ClaimExpired,
+
+    #[msg("Claim Window Still Open")]
+    ClaimNotExpired,
+
+
+    #[msg("Withdraw Exceeds Free Balance")]
+    WithdrawExceedsFree,
+
+    #[msg("Too Many Payment Mints")]
+    TooManyPaymentMints,
+
+    #[msg("Payment Mint Not Accepted")]
+    UnsupportedPaymentMint,
+
+    #[msg("Invalid Payment Account")]
+    InvalidPaymentAccount,
+
+    #[msg("Invalid Price Feed")]
+    InvalidPriceFeed,
+
+    #[msg("Price Not Trading")]
+    PriceNotTrading,
+
+    #[msg("Stale Price")]
+    StalePrice,
+
+    #[msg("Price Confidence Too Wide")]
+    PriceConfidenceTooWide,
+
+    #[msg("Too Many Fee Accruals")]
+    TooManyFeeAccruals,
+
+    #[msg("Invalid Spin Count")]
+    InvalidSpinCount,
+
+    #[msg("Invalid Voucher")]
+    InvalidVoucher,
+
+    #[msg("Voucher Expired")]
+    VoucherExpired,
+
+    #[msg("No Free Spin Available")]
+    NoFreeSpin,
+
+    #[msg("Invalid Item Table")]
+    InvalidItemTable,
+
+    #[msg("Settle Deadline Passed")]
+    SettleDeadlinePassed,
+}
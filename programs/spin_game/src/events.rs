@@ -0,0 +1,50 @@
+use anchor_lang::prelude::*;
+
+use crate::receipt::*;
+
+#[event]
+pub struct SpinSettled {
+    pub receipt: SpinReceipt,
+}
+
+// expired prizes released by reclaim_expired; their stock went back to the items.
+// Parallel lists, one entry per unclaimed prize, NATIVE_SOL_MINT for lamports.
+#[event]
+pub struct PrizeReclaimed {
+    pub pool: Pubkey,
+    pub user: Pubkey,
+    pub round_num: u32,
+    pub item_indexes: Vec<u8>,
+    pub mints: Vec<Pubkey>,
+    pub amounts: Vec<u64>,
+}
+
+// burn shares of one spin's fee schedule
+#[event]
+pub struct SpinBurned {
+    pub pool: Pubkey,
+    pub user: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub total_burned: u64,
+}
+
+// accrued fees of one mint paid out by sweep_fees
+#[event]
+pub struct FeesSwept {
+    pub pool: Pubkey,
+    pub mint: Pubkey,
+    pub wallets: Vec<Pubkey>,
+    pub amounts: Vec<u64>,
+}
+
+// a voucher spent on request_spin or spin_many
+#[event]
+pub struct VoucherRedeemed {
+    pub pool: Pubkey,
+    pub user: Pubkey,
+    pub issuer: Pubkey,
+    pub nonce: u64,
+    pub discount_bps: u16,
+    pub spin_count: u8,
+}
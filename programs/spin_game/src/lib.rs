@@ -1,20 +1,31 @@
 use anchor_lang::prelude::*;
 use anchor_spl::{
-    associated_token::AssociatedToken,
+    associated_token::{self, AssociatedToken},
 };
-use anchor_spl::token::{self, Mint, Token, TokenAccount, Transfer};
-use solana_program::{program::invoke, program::invoke_signed, system_instruction};
+use anchor_spl::token_interface::{self, Burn, Mint, TokenAccount, TokenInterface, TransferChecked};
+use solana_program::{program::invoke, program::invoke_signed, system_instruction, sysvar};
+use solana_program::hash::hash;
 use std::mem::size_of;
 
 pub mod account;
 pub mod constants;
 pub mod errors;
+pub mod events;
+pub mod price_feed;
+pub mod randomness;
+pub mod receipt;
 pub mod utils;
+pub mod voucher;
 
 use account::*;
 use constants::*;
 use errors::*;
+use events::*;
+use price_feed::*;
+use randomness::*;
+use receipt::*;
 use utils::*;
+use voucher::*;
 
 declare_id!("BgNAhuXboSjPugSwmVu43yPXEXguZF7ToNePWuzCTcZe");
 
@@ -24,14 +35,24 @@ pub mod spin_game {
 
     pub fn initialize(
         ctx: Context<Initialize>,
+        wheel_id: u64,
     ) -> Result<()> {
 
         let pool = &mut ctx.accounts.pool;
+        pool.wheel_id = wheel_id;
         pool.superadmin = ctx.accounts.super_admin.key();
-        pool.dev_wallet = Pubkey::try_from(DEV_WALLET_KEY).unwrap();
-        pool.dev_fee = 3; // means 3%
-
-        let mut _state = ctx.accounts.state.load_init()?;
+        pool.set_fee_schedule(&[FeeRecipient {
+            wallet: Pubkey::try_from(DEV_WALLET_KEY).unwrap(),
+            bps: DEFAULT_DEV_FEE_BPS,
+            label: *b"dev\0\0\0\0\0\0\0\0\0\0\0\0\0",
+            burn: false,
+        }])?;
+        pool.ratio_total = DEFAULT_RATIO_TOTAL;
+        pool.max_price_age_slots = DEFAULT_MAX_PRICE_AGE_SLOTS;
+        pool.max_price_conf_bps = DEFAULT_MAX_PRICE_CONF_BPS;
+
+        let mut state = ctx.accounts.state.load_init()?;
+        state.capacity = SPIN_ITEM_COUNT as u8;
 
         invoke(
             &system_instruction::transfer(&ctx.accounts.initializer.key(), &ctx.accounts.vault.key(), 10000000),
@@ -45,13 +66,11 @@ pub mod spin_game {
         Ok(())
     }
 
+    // admins and the superadmin may reprice; a zero price stops accepting the mint
     pub fn set_pay_info(
         ctx: Context<SetPayInfo>,
+        payment_mint: Pubkey,
         price: u64,
-        dev_fee: u64,
-        dev_wallet: Pubkey,
-        burn_fee: u64,
-        burn_wallet: Pubkey,
     ) -> Result<()> {
         let accts = ctx.accounts;
         let pool = &mut accts.pool;
@@ -67,20 +86,106 @@ pub mod spin_game {
         require!(is_admin || pool.superadmin.eq(&accts.admin.key()),
             SpinError::IncorrectSuperAdminOrAdmin
         );
-        require!(burn_fee + dev_fee < 100 * PERCENT_MULTIPLIER, SpinError::IncorrectFee);
 
-        pool.price = price;
+        pool.set_payment_option(payment_mint, price)?;
 
-        if pool.superadmin.eq(&accts.admin.key()) {
-            pool.dev_fee = dev_fee;
-            pool.dev_wallet = dev_wallet;
-            pool.burn_fee = burn_fee;
-            pool.burn_wallet = burn_wallet;
-        }
+        Ok(())
+    }
+
+    pub fn set_fee_schedule(ctx: Context<ConfigurePool>, recipients: Vec<FeeRecipient>) -> Result<()> {
+        ctx.accounts.pool.set_fee_schedule(&recipients)
+    }
+
+    pub fn set_randomness_source(
+        ctx: Context<ConfigurePool>,
+        randomness_source: u8,
+        oracle: Pubkey,
+    ) -> Result<()> {
+        let valid = randomness_source == RANDOMNESS_SOURCE_SLOT_HASHES
+            || randomness_source == RANDOMNESS_SOURCE_ORACLE
+            || (cfg!(feature = "test-rng") && randomness_source == RANDOMNESS_SOURCE_SEEDED);
+        require!(valid, SpinError::InvalidRandomnessSource);
+
+        let pool = &mut ctx.accounts.pool;
+        pool.randomness_source = randomness_source;
+        pool.oracle = oracle;
+
+        Ok(())
+    }
+
+    // set_pay_info's price for `payment_mint` is read in `price_mode`; USD prices
+    // are converted at spin time through the Pyth account `price_feed`
+    pub fn set_price_mode(ctx: Context<ConfigurePool>, payment_mint: Pubkey, price_mode: u8, price_feed: Pubkey) -> Result<()> {
+        require!(
+            price_mode == PRICE_MODE_TOKEN || (price_mode == PRICE_MODE_USD_CENTS && price_feed != Pubkey::default()),
+            SpinError::InvalidPriceFeed
+        );
+        let option = ctx.accounts.pool.payment_option_mut(&payment_mint).ok_or(SpinError::UnsupportedPaymentMint)?;
+        option.price_mode = price_mode;
+        option.price_feed = price_feed;
+
+        Ok(())
+    }
+
+    pub fn set_price_limits(ctx: Context<ConfigurePool>, max_price_age_slots: u64, max_price_conf_bps: u16) -> Result<()> {
+        require!(max_price_conf_bps as u64 <= BPS_DENOMINATOR, SpinError::InvalidPriceFeed);
+        let pool = &mut ctx.accounts.pool;
+        pool.max_price_age_slots = max_price_age_slots;
+        pool.max_price_conf_bps = max_price_conf_bps;
+
+        Ok(())
+    }
+
+    pub fn set_claim_window(ctx: Context<ConfigurePool>, claim_window: i64) -> Result<()> {
+        require!(claim_window >= 0, SpinError::InvalidClaimWindow);
+        ctx.accounts.pool.claim_window = claim_window;
+
+        Ok(())
+    }
+
+    pub fn set_reserve_bps(ctx: Context<ConfigurePool>, reserve_bps: u16) -> Result<()> {
+        require!(reserve_bps as u64 <= BPS_DENOMINATOR, SpinError::IncorrectFee);
+        ctx.accounts.pool.reserve_bps = reserve_bps;
+
+        Ok(())
+    }
+
+    // replaces the keys allowed to sign vouchers, an empty list stops voucher spins
+    pub fn set_voucher_issuers(ctx: Context<ConfigurePool>, issuers: Vec<Pubkey>) -> Result<()> {
+        ctx.accounts.pool.set_voucher_issuers(&issuers)
+    }
+
+    // `allowance` free spins per user every `period` seconds, 0 allowance to disable.
+    // With use_free_table they draw from the table made by init_free_items.
+    pub fn set_free_spins(ctx: Context<ConfigurePool>, allowance: u8, period: i64, use_free_table: bool) -> Result<()> {
+        require!(allowance == 0 || period > 0, SpinError::NoFreeSpin);
+        let pool = &mut ctx.accounts.pool;
+        pool.free_spin_allowance = allowance;
+        pool.free_spin_period = period;
+        pool.free_spin_table = use_free_table;
 
         Ok(())
     }
 
+    // separate item table for free spins, filled with add_item and friends like the paid one
+    pub fn init_free_items(ctx: Context<InitFreeItems>) -> Result<()> {
+        let mut state = ctx.accounts.state.load_init()?;
+        state.capacity = SPIN_ITEM_COUNT as u8;
+
+        Ok(())
+    }
+
+    // spin_many batches of at least `min_spins` draws cost `discount_bps` less, 0 min_spins for no discount
+    pub fn set_bulk_discount(ctx: Context<ConfigurePool>, min_spins: u8, discount_bps: u16) -> Result<()> {
+        require!((discount_bps as u64) < BPS_DENOMINATOR, SpinError::IncorrectFee);
+        let pool = &mut ctx.accounts.pool;
+        pool.bulk_discount_min = min_spins;
+        pool.bulk_discount_bps = discount_bps;
+
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
     pub fn add_item(
         ctx: Context<SpinWheel>,
         item_mint_list: [Pubkey; 10],
@@ -88,14 +193,24 @@ pub mod spin_game {
         token_type: u8,
         ratio: u32,
         amount: u64,
+        stock: u32,
+        collection: Pubkey,
     ) -> Result<()> {
 
-        let mut state = ctx.accounts.state.load_mut()?;
-        state.add_spinitem(ItemRewardMints{item_mint_list, count}, token_type, ratio, amount)?;
+        let mut reward_mints = ItemRewardMints { item_mint_list, count, ..Default::default() };
+        fn_reward_decimals(&mut reward_mints, ctx.remaining_accounts)?;
+        let item = SpinItem { reward_mints, token_type, ratio, amount, stock, collection };
+        item.prize_amount()?;
+
+        let state_info = ctx.accounts.state.to_account_info();
+        let mut data = state_info.try_borrow_mut_data()?;
+        let (state, items) = SpinItemList::from_data_mut(&mut data);
+        state.add_spinitem(items, item, ctx.accounts.pool.ratio_total)?;
 
         Ok(())
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn set_item(
         ctx: Context<SpinWheel>,
         index: u8,
@@ -104,184 +219,641 @@ pub mod spin_game {
         token_type: u8,
         ratio: u32,
         amount: u64,
+        stock: u32,
+        collection: Pubkey,
         item_count: u8,
     ) -> Result<()> {
 
-        let mut state = ctx.accounts.state.load_mut()?;
-        state.set_spinitem(index, ItemRewardMints{item_mint_list, count}, token_type, ratio, amount)?;
+        let mut reward_mints = ItemRewardMints { item_mint_list, count, ..Default::default() };
+        fn_reward_decimals(&mut reward_mints, ctx.remaining_accounts)?;
+        let item = SpinItem { reward_mints, token_type, ratio, amount, stock, collection };
+        item.prize_amount()?;
+
+        let state_info = ctx.accounts.state.to_account_info();
+        let mut data = state_info.try_borrow_mut_data()?;
+        let (state, items) = SpinItemList::from_data_mut(&mut data);
+        state.set_spinitem(items, index, item)?;
+        require!(item_count <= state.capacity, SpinError::IndexOverflowSetItem);
         state.count = item_count;
 
         Ok(())
     }
 
-    pub fn spin_wheel(ctx: Context<PlayGame>, rand: u32, _round_id: u64) -> Result<()> {
+    // reward vault for one mint; fund it with a plain transfer
+    pub fn init_reward_vault(_ctx: Context<InitRewardVault>) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn deposit_nft<'info>(ctx: Context<'_, '_, '_, 'info, DepositNft<'info>>, index: u8) -> Result<()> {
         let accts = ctx.accounts;
-        let pay_amount = accts.pool.price;
 
-        let dev_fee = fn_dev_fee(&accts.pool, accts.pool.price)?;
-        let burn_fee = fn_burn_fee(&accts.pool, accts.pool.price)?;
-        let vault_fee = accts.pool.price.checked_sub(dev_fee + burn_fee).unwrap();
+        {
+            let state_info = accts.state.to_account_info();
+            let mut data = state_info.try_borrow_mut_data()?;
+            let (state, items) = SpinItemList::from_data_mut(&mut data);
+            require!(index < state.count, SpinError::IndexOverflowSetItem);
+
+            let item = &mut items[index as usize];
+            require!(item.token_type == TOKEN_TYPE_NFT, SpinError::InvalidNft);
+            // claim checks the collection again, but a stray NFT is caught before it sits in the escrow
+            fn_check_collection(&accts.nft_mint.key(), &item.collection, accts.metadata.as_ref().map(|m| m.as_ref()))?;
+            item.reward_mints.add_reward_item(accts.nft_mint.key(), accts.nft_mint.decimals)?;
+        }
 
-        // pay
-        anchor_spl::token::transfer(CpiContext::new(
+        token_interface::transfer_checked(CpiContext::new(
             accts.token_program.to_account_info(),
-            anchor_spl::token::Transfer {
+            TransferChecked {
                 from: accts.source_account.to_account_info(),
-                to: accts.fronk_dev.to_account_info(),
-                authority: accts.user.to_account_info(),
+                mint: accts.nft_mint.to_account_info(),
+                to: accts.escrow_account.to_account_info(),
+                authority: accts.superadmin.to_account_info(),
             },
-        ), dev_fee)?;
+        ).with_remaining_accounts(ctx.remaining_accounts.to_vec()), 1, 0)?;
 
-        anchor_spl::token::transfer(CpiContext::new(
-            accts.token_program.to_account_info(),
-            anchor_spl::token::Transfer {
-                from: accts.source_account.to_account_info(),
-                to: accts.fronk_burn.to_account_info(),
-                authority: accts.user.to_account_info(),
-            },
-        ), burn_fee)?;
+        Ok(())
+    }
 
-        anchor_spl::token::transfer(CpiContext::new(
-            accts.token_program.to_account_info(),
-            anchor_spl::token::Transfer {
-                from: accts.source_account.to_account_info(),
-                to: accts.fronk_vault.to_account_info(),
-                authority: accts.user.to_account_info(),
-            },
-        ), vault_fee)?;
+    pub fn restock_item(ctx: Context<SpinWheel>, index: u8, amount: u32) -> Result<()> {
+        let state_info = ctx.accounts.state.to_account_info();
+        let mut data = state_info.try_borrow_mut_data()?;
+        let (state, items) = SpinItemList::from_data_mut(&mut data);
+        require!(index < state.count, SpinError::IndexOverflowSetItem);
+        items[index as usize].restock(amount)?;
 
-        msg!("transfer success");
-        if accts.user_state.is_initialized == 0 {
-            accts.user_state.is_initialized = 1;
-            accts.user_state.user = accts.user.key();
-            accts.user_state.round_num = 1;
-        } else {
-            require!(
-                accts.user_state.user.eq(&accts.user.key()),
-                SpinError::IncorrectUserState
-            );
-            accts.user_state.round_num = accts.user_state.round_num + 1;
+        Ok(())
+    }
+
+    pub fn set_ratio_total(ctx: Context<SpinWheel>, ratio_total: u64) -> Result<()> {
+        require!(ratio_total > 0, SpinError::InvalidRatioTable);
+        ctx.accounts.pool.ratio_total = ratio_total;
+
+        Ok(())
+    }
+
+    pub fn resize_items(ctx: Context<ResizeItems>, capacity: u8) -> Result<()> {
+        // the realloc constraint already resized the account to SpinItemList::space(capacity)
+        let mut state = ctx.accounts.state.load_mut()?;
+        require!(capacity >= state.count, SpinError::CapacityBelowItemCount);
+        state.capacity = capacity;
+
+        Ok(())
+    }
+
+    // open pending accounts of a user on this wheel, oldest first
+    pub fn pending_rounds(ctx: Context<ViewPendingRounds>) -> Result<Vec<PendingRound>> {
+        let user_state = &ctx.accounts.user_state;
+        let pool_key = ctx.accounts.pool.key();
+        Ok(user_state.pending_rounds[..user_state.pending_count as usize]
+            .iter()
+            .map(|round_num| PendingRound {
+                round_num: *round_num,
+                address: Pubkey::find_program_address(
+                    &[&round_num.to_le_bytes(), pool_key.as_ref(), user_state.user.as_ref()],
+                    ctx.program_id,
+                ).0,
+            })
+            .collect())
+    }
+
+    pub fn odds(ctx: Context<ViewOdds>) -> Result<SpinOdds> {
+        let state_info = ctx.accounts.state.to_account_info();
+        let data = state_info.try_borrow_data()?;
+        let (state, items) = SpinItemList::from_data(&data);
+        Ok(state.odds(items, ctx.accounts.pool.ratio_total))
+    }
+
+    // pays the price listed for `payment_mint`, NATIVE_SOL_MINT for lamports.
+    // USD-priced options also need their Pyth feed.
+    // SPL payments need the optional payment accounts, SOL payments leave them out.
+    // remaining_accounts: the reward vault of every mint in SpinItemList::prize_mints
+    // for one draw, then any Token-2022 transfer hook accounts of the payment mint.
+    // A voucher also needs used_voucher and the instructions sysvar; fully
+    // discounted spins need no payment accounts at all.
+    pub fn request_spin<'info>(
+        ctx: Context<'_, '_, '_, 'info, RequestSpin<'info>>,
+        payment_mint: Pubkey,
+        commitment: [u8; 32],
+        voucher: Option<Voucher>,
+    ) -> Result<()> {
+        ctx.accounts.pay_and_commit(ctx.program_id, ctx.remaining_accounts, payment_mint, commitment, voucher, 1)
+    }
+
+    // request_spin for `spin_count` draws under one commitment and one pending
+    // account, at spin_count times the price less the pool's bulk discount
+    // and then the voucher's on up to the voucher's spin_count of them.
+    // The reward vaults cover SpinItemList::prize_mints for spin_count draws.
+    pub fn spin_many<'info>(
+        ctx: Context<'_, '_, '_, 'info, RequestSpin<'info>>,
+        payment_mint: Pubkey,
+        commitment: [u8; 32],
+        voucher: Option<Voucher>,
+        spin_count: u8,
+    ) -> Result<()> {
+        require!(spin_count > 0 && spin_count <= MAX_BATCH_SPINS, SpinError::InvalidSpinCount);
+        ctx.accounts.pay_and_commit(ctx.program_id, ctx.remaining_accounts, payment_mint, commitment, voucher, spin_count)
+    }
+
+    // one draw paid for by the pool's free-spin allowance, settled like any other spin
+    // remaining_accounts: the reward vault of every mint in SpinItemList::prize_mints
+    pub fn free_spin(ctx: Context<FreeSpin>, commitment: [u8; 32]) -> Result<()> {
+        let accts = ctx.accounts;
+        let (affordable_mask, reservation, _) = fn_snapshot_draw(
+            ctx.program_id,
+            &accts.pool,
+            &accts.state.to_account_info(),
+            &accts.vault,
+            ctx.remaining_accounts,
+            1,
+        )?;
+        accts.user_state.take_free_spin(&accts.pool, Clock::get()?.unix_timestamp)?;
+        let round_num = accts.user_state.begin_round(accts.user.key())?;
+
+        let pending = &mut accts.user_pendingstate;
+        pending.user = accts.user.key();
+        pending.pool = accts.pool.key();
+        pending.round_num = round_num;
+        pending.commitment = commitment;
+        pending.reveal_slot = Clock::get()?.slot + SPIN_REVEAL_DELAY_SLOTS;
+        pending.item_table = accts.state.key();
+        pending.affordable_mask = affordable_mask;
+        pending.randomness_source = accts.pool.randomness_source;
+        pending.oracle = accts.pool.oracle;
+        pending.settle_deadline = fn_settle_deadline(pending.randomness_source, pending.reveal_slot);
+        pending.spin_count = 1;
+
+        for liability in &reservation {
+            accts.pool.add_liability(liability.mint, liability.amount)?;
         }
+        pending.reserved = reservation;
+
+        let space = UserPendingClaimState::space(1, pending.reserved.len());
+        let user = accts.user.to_account_info();
+        let system_program = accts.system_program.to_account_info();
+        fn_grow_account(&accts.user_pendingstate.to_account_info(), &user, &system_program, space)?;
+        fn_grow_account(&accts.pool.to_account_info(), &user, &system_program, accts.pool.space())
+    }
+
+    pub fn settle_spin(ctx: Context<SettleSpin>, secret: [u8; 32]) -> Result<()> {
+        let accts = ctx.accounts;
+        let pending_key = accts.user_pendingstate.key();
+        let pending = &mut accts.user_pendingstate;
+
+        require!(!pending.is_settled, SpinError::SpinAlreadySettled);
+        require!(
+            pending.settle_deadline == 0 || Clock::get()?.slot <= pending.settle_deadline,
+            SpinError::SettleDeadlinePassed
+        );
+        require!(hash(&secret).to_bytes() == pending.commitment, SpinError::InvalidReveal);
 
         // generate random winner
-        let pyth_price_info = &accts.pyth_account;
-        let pyth_price_data = &pyth_price_info.try_borrow_data()?;
-        let pyth_price = pyth_client::cast::<pyth_client::Price>(pyth_price_data);
-        let agg_price = pyth_price.agg.price as u64;
-        let mut rand_spin = agg_price + rand as u64;
-        let mut amount: u64 = 0;
-
-        let mut state = accts.state.load_mut()?;
-
-        loop {
-            state.get_spinresult(rand_spin as u32);
-            amount = state.amount_list[state.last_spinindex as usize];
-            if amount < accts.fronk_vault.amount / 2 {
-                break;
-            }
-            rand_spin += 1;
-        }
-        let last_spinindex = state.last_spinindex as usize;
-        let reward_mints = state.reward_mint_list[last_spinindex as usize];
-
-        msg!("last_spinindex: {}", last_spinindex);
-        accts.user_pendingstate.user = accts.user.key();
-        accts.user_pendingstate.is_claimed = 0;
-        accts.user_pendingstate.round_num = accts.user_state.round_num;
-        accts.user_pendingstate.is_sol = state.token_type_list[last_spinindex] == 2;
-        if accts.user_pendingstate.is_sol {
-            accts.user_pendingstate.sol_amount = amount;
+        let source: Box<dyn RandomnessSource + '_> = match pending.randomness_source {
+            RANDOMNESS_SOURCE_SLOT_HASHES => Box::new(SlotHashesSource {
+                slot_hashes: &accts.slot_hashes,
+                slot: pending.reveal_slot,
+            }),
+            RANDOMNESS_SOURCE_ORACLE => Box::new(OracleSource {
+                instructions: &accts.instructions,
+                oracle: pending.oracle,
+                request: pending_key,
+            }),
+            #[cfg(feature = "test-rng")]
+            RANDOMNESS_SOURCE_SEEDED => Box::new(SeededSource {
+                seed: pending.round_num as u64,
+            }),
+            _ => return err!(SpinError::InvalidRandomnessSource),
+        };
+        let entropy = source.entropy()?;
+        pending.entropy_slot = entropy.slot;
+
+        let state_info = accts.state.to_account_info();
+        let mut data = state_info.try_borrow_mut_data()?;
+        let (state, items) = SpinItemList::from_data_mut(&mut data);
+        require!(state.ratio_sum(items) == accts.pool.ratio_total, SpinError::InvalidRatioTable);
+
+        // what the request reserved is given back first and replaced by the prizes won
+        for liability in std::mem::take(&mut pending.reserved) {
+            accts.pool.release_liability(&liability.mint, liability.amount);
         }
 
-        let mut one_rmint = Pubkey::default();
-        if reward_mints.count > 0 {
-            one_rmint = reward_mints.item_mint_list[0];
-            accts.user_pendingstate.add_item(reward_mints.item_mint_list[(reward_mints.count - 1) as usize], amount)?;
-            if state.token_type_list[last_spinindex as usize] == 1{
-                state.reward_mint_list[last_spinindex as usize].count -= 1;
+        // the draw uses the items fixed at request time; the vaults are only
+        // read to check the drawn prize can still be paid
+        let (mut sol_balance, mut token_balances) = fn_free_balances(ctx.program_id, &accts.pool, &accts.vault, ctx.remaining_accounts)?;
+        let weights = state.masked_weights(items, &pending.affordable_mask);
+
+        pending.is_settled = true;
+        if accts.pool.claim_window > 0 {
+            pending.expires_at = Clock::get()?.unix_timestamp + accts.pool.claim_window;
+        }
+        let pay_per_spin = pending.pay_amount / pending.spin_count as u64;
+
+        for draw_index in 0..pending.spin_count {
+            let mut receipt = SpinReceipt {
+                pool: accts.pool.key(),
+                user: accts.user.key(),
+                round_num: pending.round_num,
+                draw_index,
+                randomness_source: pending.randomness_source,
+                commitment: pending.commitment,
+                secret,
+                entropy: entropy.value,
+                reveal_slot: pending.reveal_slot,
+                entropy_slot: entropy.slot,
+                request: pending_key,
+                oracle: pending.oracle,
+                oracle_signature: entropy.signature.clone(),
+                ratio_list: items[..state.count as usize].iter().map(|item| item.ratio).collect(),
+                weights: weights.clone(),
+                redraw_weights: vec![],
+                last_spinindex: 0,
+            };
+            receipt.last_spinindex = verify_spin(&receipt).ok_or(SpinError::NoAffordableItem)?;
+
+            // the reservation keeps the amounts covered, but the item can still have run
+            // out of stock or mint slots since the request, or earlier draws of the batch
+            // used it up. Roll again from the same seed over the items still payable.
+            if !items[receipt.last_spinindex as usize].is_affordable(sol_balance, &token_balances)? {
+                let mut redraw_weights = weights.clone();
+                for (weight, item) in redraw_weights.iter_mut().zip(items.iter()) {
+                    if *weight > 0 && !item.is_affordable(sol_balance, &token_balances)? {
+                        *weight = 0;
+                    }
+                }
+                msg!("item {} can no longer be paid, redrawing", receipt.last_spinindex);
+                receipt.redraw_weights = redraw_weights;
+                receipt.last_spinindex = verify_spin(&receipt).ok_or(SpinError::NoAffordableItem)?;
             }
+            state.last_spinindex = receipt.last_spinindex;
+            let last_spinindex = state.last_spinindex as usize;
+            msg!("last_spinindex: {}", last_spinindex);
+
+            let item = &mut items[last_spinindex];
+            let token_type = item.token_type;
+            let reward_mints = item.reward_mints;
+            let one_rmint = if reward_mints.count > 0 { reward_mints.item_mint_list[0] } else { Pubkey::default() };
+            let amount = item.prize_amount()?;
+            item.take_stock();
+
+            let mut prize = PendingPrize {
+                amount,
+                token_type,
+                item_index: receipt.last_spinindex,
+                collection: item.collection,
+                ..Default::default()
+            };
+            // NATIVE_SOL_MINT is the default key, so whether anything was won
+            // follows the item rather than prize.mint
+            let has_prize = token_type == TOKEN_TYPE_SOL || reward_mints.count > 0;
+            if token_type == TOKEN_TYPE_SOL {
+                prize.mint = NATIVE_SOL_MINT;
+                sol_balance = sol_balance.saturating_sub(amount);
+            } else if reward_mints.count > 0 {
+                let last = (reward_mints.count - 1) as usize;
+                prize.mint = reward_mints.item_mint_list[last];
+                prize.decimals = reward_mints.decimals_list[last];
+                if let Some((_, balance)) = token_balances.iter_mut().find(|(mint, _)| *mint == prize.mint) {
+                    *balance = balance.saturating_sub(amount);
+                }
+                if token_type == TOKEN_TYPE_MINT_LIST || token_type == TOKEN_TYPE_NFT {
+                    item.reward_mints.count -= 1;
+                }
+            }
+            if has_prize {
+                pending.add_prize(prize)?;
+                accts.pool.add_liability(prize.mint, amount)?;
+            }
+
+            accts.last_users.push_front_last_user(accts.user.key(), pay_per_spin, amount, one_rmint, token_type)?;
+
+            emit!(SpinSettled { receipt });
         }
 
-        accts.last_users.push_front_last_user(accts.user.key(), pay_amount, amount, one_rmint, state.token_type_list[last_spinindex as usize])?;
+        fn_grow_account(
+            &accts.pool.to_account_info(),
+            &accts.user.to_account_info(),
+            &accts.system_program.to_account_info(),
+            accts.pool.space(),
+        )?;
+
+        // nothing to claim, so the round ends here
+        if !pending.has_unclaimed() {
+            accts.user_state.remove_pending_round(pending.round_num);
+            return accts.user_pendingstate.close(accts.user.to_account_info());
+        }
 
         Ok(())
     }
 
-    pub fn claim(
-        ctx : Context<Claim>,
-        amount: u64,
-        is_sol: bool,
-        bump: u8
-        ) -> Result<()> {
+    // pays every SOL prize of a settled spin in one transfer; the pending account
+    // is closed to the user once nothing is left on it
+    pub fn claim_sol(ctx: Context<ClaimSol>) -> Result<()> {
+        let accts = ctx.accounts;
+        let pending = &mut accts.user_pendingstate;
+        require!(pending.is_settled, SpinError::InvalidReward);
+        require!(!pending.is_expired(Clock::get()?.unix_timestamp), SpinError::ClaimExpired);
+
+        let sol_amount = pending.take_unclaimed_sol();
+        require!(sol_amount > 0, SpinError::InvalidReward);
+        accts.pool.release_liability(&NATIVE_SOL_MINT, sol_amount);
+
+        let pool_key = accts.pool.key();
+        invoke_signed(
+            &system_instruction::transfer(&accts.vault.key(), &accts.owner.key(), sol_amount),
+            &[
+                accts.vault.to_account_info(),
+                accts.owner.to_account_info(),
+                accts.system_program.to_account_info(),
+            ],
+            &[&[VAULT_SEED, pool_key.as_ref(), &[ctx.bumps.vault]]],
+        )?;
+
+        // the last prize ends the round
+        if !pending.has_unclaimed() {
+            accts.user_state.remove_pending_round(pending.round_num);
+            return accts.user_pendingstate.close(accts.owner.to_account_info());
+        }
+
+        Ok(())
+    }
+
+    // pays one pending token prize; the amount always comes from the pending account,
+    // which is closed to the user once nothing is left on it. SOL prizes go through claim_sol.
+    pub fn claim<'info>(ctx: Context<'_, '_, '_, 'info, Claim<'info>>) -> Result<()> {
         let user_pendingstate = &mut ctx.accounts.user_pendingstate;
+        require!(user_pendingstate.is_settled, SpinError::InvalidReward);
+        require!(!user_pendingstate.is_expired(Clock::get()?.unix_timestamp), SpinError::ClaimExpired);
+
+        let reward_mint = ctx.accounts.source_reward_account.mint;
+        let index = user_pendingstate
+            .next_unclaimed(&reward_mint)
+            .ok_or(SpinError::InvalidReward)?;
+        let prize = &mut user_pendingstate.prizes[index];
+        prize.is_claimed = true;
+
+        // stored in base units at settle time, so it is paid out as is
+        let transfer_amount = prize.amount;
+        ctx.accounts.pool.release_liability(&reward_mint, transfer_amount);
+        if prize.token_type == TOKEN_TYPE_NFT {
+            require!(ctx.accounts.reward_mint.decimals == 0, SpinError::InvalidNft);
+            fn_check_collection(
+                &reward_mint,
+                &prize.collection,
+                ctx.accounts.metadata.as_ref().map(|m| m.as_ref()),
+            )?;
+        }
+
+        let wheel_id = ctx.accounts.pool.wheel_id.to_le_bytes();
+        let authority_seeds = &[ESCROW_PDA_SEED.as_bytes(), &wheel_id, &[ctx.bumps.pool]];
+
+        token_interface::transfer_checked(
+            ctx.accounts.transfer_from_pda_context()
+                .with_signer(&[&authority_seeds[..]])
+                .with_remaining_accounts(ctx.remaining_accounts.to_vec()),
+            transfer_amount,
+            ctx.accounts.reward_mint.decimals,
+        )?;
+
+        // the last prize ends the round
+        if !ctx.accounts.user_pendingstate.has_unclaimed() {
+            let round_num = ctx.accounts.user_pendingstate.round_num;
+            ctx.accounts.user_state.remove_pending_round(round_num);
+            return ctx.accounts.user_pendingstate.close(ctx.accounts.owner.clone());
+        }
 
-        if is_sol == true {
-            require!(user_pendingstate.is_sol && user_pendingstate.is_claimed == 0 && amount == user_pendingstate.sol_amount, SpinError::InvalidReward);
+        Ok(())
+    }
 
-            // let bump = ctx.bumps.get("vault").unwrap();
+    // Pays every unclaimed prize of a settled spin and closes the pending account
+    // to the user. remaining_accounts hold, per unclaimed token prize in pending order,
+    // (reward vault, user token account, mint), followed by the mint's metadata for
+    // NFT prizes with a collection. Missing user token accounts are created at the
+    // user's expense. Mints with transfer hooks go through claim.
+    pub fn claim_all<'info>(ctx: Context<'_, '_, '_, 'info, ClaimAll<'info>>) -> Result<()> {
+        let accts = ctx.accounts;
+        let pending = &mut accts.user_pendingstate;
+        require!(pending.is_settled, SpinError::InvalidReward);
+        require!(!pending.is_expired(Clock::get()?.unix_timestamp), SpinError::ClaimExpired);
+
+        let pool_key = accts.pool.key();
+        let sol_amount = pending.take_unclaimed_sol();
+        if sol_amount > 0 {
+            accts.pool.release_liability(&NATIVE_SOL_MINT, sol_amount);
             invoke_signed(
-                &system_instruction::transfer(&ctx.accounts.vault.key(), &ctx.accounts.owner.key(), amount),
+                &system_instruction::transfer(&accts.vault.key(), &accts.owner.key(), sol_amount),
                 &[
-                    ctx.accounts.vault.to_account_info().clone(),
-                    ctx.accounts.owner.clone(),
-                    ctx.accounts.system_program.to_account_info().clone(),
+                    accts.vault.to_account_info(),
+                    accts.owner.to_account_info(),
+                    accts.system_program.to_account_info(),
                 ],
-                &[&[VAULT_SEED, &[bump]]],
+                &[&[VAULT_SEED, pool_key.as_ref(), &[ctx.bumps.vault]]],
             )?;
-        } else {
-            let reward_mint = ctx.accounts.source_reward_account.mint;
-            let mut is_found = false;
-            // let mut found_idx = 0;
+        }
+
+        let wheel_id = accts.pool.wheel_id.to_le_bytes();
+        let authority_seeds = &[ESCROW_PDA_SEED.as_bytes(), &wheel_id, &[ctx.bumps.pool]];
+
+        let mut remaining = ctx.remaining_accounts.iter();
+        for prize in pending.prizes.iter_mut().filter(|p| !p.is_claimed) {
+            let reward_mint = prize.mint;
+            let (Some(source), Some(dest), Some(mint)) = (remaining.next(), remaining.next(), remaining.next()) else {
+                return err!(SpinError::MissingRewardVault);
+            };
+
+            require!(mint.key() == reward_mint, SpinError::MissingRewardMint);
+            if dest.data_is_empty() {
+                associated_token::create(CpiContext::new(
+                    accts.associated_token_program.to_account_info(),
+                    associated_token::Create {
+                        payer: accts.owner.to_account_info(),
+                        associated_token: dest.clone(),
+                        authority: accts.owner.to_account_info(),
+                        mint: mint.clone(),
+                        system_program: accts.system_program.to_account_info(),
+                        token_program: accts.token_program.to_account_info(),
+                    },
+                ))?;
+            }
+
+            let source_account = fn_token_account(source, &accts.token_program.key())?;
+            let dest_account = fn_token_account(dest, &accts.token_program.key())?;
+            require!(
+                source.key() == fn_reward_vault_address(ctx.program_id, &pool_key, &reward_mint)
+                    && source_account.mint == reward_mint
+                    && source_account.owner == pool_key,
+                SpinError::InvalidRewardVault
+            );
+            require!(dest_account.mint == reward_mint && dest_account.owner == accts.owner.key(), SpinError::InvalidReward);
+            let decimals = Mint::try_deserialize(&mut &mint.try_borrow_data()?[..])?.decimals;
+
+            if prize.token_type == TOKEN_TYPE_NFT {
+                require!(decimals == 0, SpinError::InvalidNft);
+                let metadata = if prize.collection == Pubkey::default() { None } else { remaining.next() };
+                fn_check_collection(&reward_mint, &prize.collection, metadata)?;
+            }
+
+            prize.is_claimed = true;
+            accts.pool.release_liability(&reward_mint, prize.amount);
+            token_interface::transfer_checked(
+                CpiContext::new(
+                    accts.token_program.to_account_info(),
+                    TransferChecked {
+                        from: source.clone(),
+                        mint: mint.clone(),
+                        to: dest.clone(),
+                        authority: accts.pool.to_account_info(),
+                    },
+                ).with_signer(&[&authority_seeds[..]]),
+                prize.amount,
+                decimals,
+            )?;
+        }
 
-            for i in 0..user_pendingstate.count {
-                if user_pendingstate.is_claimed_list[i as usize] || !user_pendingstate.pending_mint_list[i as usize].eq(&reward_mint) {
+        accts.user_state.remove_pending_round(pending.round_num);
+        pending.close(accts.owner.to_account_info())
+    }
+
+    // Permissionless: once prizes are past their claim window, puts their stock (and
+    // the mint slot for mint-list and NFT items) back on the items they were drawn
+    // from and closes the pending account, refunding rent to the user. The
+    // earmarked tokens and lamports never left the vaults, so they are free again.
+    // Spins never settled before their settle_deadline are closed the same way,
+    // without a refund.
+    pub fn reclaim_expired(ctx: Context<ReclaimExpired>) -> Result<()> {
+        let accts = ctx.accounts;
+        let pending = &accts.user_pendingstate;
+        require!(
+            (pending.is_settled && pending.is_expired(Clock::get()?.unix_timestamp))
+                || pending.missed_settle_deadline(Clock::get()?.slot),
+            SpinError::ClaimNotExpired
+        );
+
+        let unclaimed: Vec<PendingPrize> = pending.prizes.iter().filter(|p| !p.is_claimed).copied().collect();
+        if !unclaimed.is_empty() {
+            let state_info = accts.state.to_account_info();
+            let mut data = state_info.try_borrow_mut_data()?;
+            let (state, items) = SpinItemList::from_data_mut(&mut data);
+
+            for prize in &unclaimed {
+                // skip items that were reconfigured since the draw
+                if prize.item_index >= state.count {
                     continue;
                 }
-
-                if (
-                    reward_mint == Pubkey::try_from(FRONK_MINT).unwrap() && 
-                    user_pendingstate.pending_amount_list[i as usize] == amount) || (
-                    reward_mint != Pubkey::try_from(FRONK_MINT).unwrap() && 
-                    user_pendingstate.pending_amount_list[i as usize] == amount * ((10 as u32).pow(REWARD_TOKEN_DECIMAL as u32)) as u64
-                ) {
-                    is_found = true;
-                    // found_idx = i;
-                    user_pendingstate.is_claimed_list[i as usize] = true;
-                    break;
+                let item = &mut items[prize.item_index as usize];
+                let token_type = item.token_type;
+                if token_type != prize.token_type {
+                    continue;
+                }
+                item.restock(1)?;
+                if token_type == TOKEN_TYPE_MINT_LIST || token_type == TOKEN_TYPE_NFT {
+                    item.reward_mints.add_reward_item(prize.mint, prize.decimals)?;
                 }
             }
+        }
 
-            // require!(is_found, SpinError::InvalidReward);
-            if is_found && ctx.accounts.source_reward_account.amount > 0 {
-                let (_vault_authority, vault_authority_bump) =
-                Pubkey::find_program_address(&[ESCROW_PDA_SEED.as_ref()], ctx.program_id);
-                let authority_seeds = &[&ESCROW_PDA_SEED.as_bytes()[..], &[vault_authority_bump]];
-    
-                token::transfer(
-                    ctx.accounts.into_transfer_to_pda_context()
-                        .with_signer(&[&authority_seeds[..]]),
-                amount,
+        emit!(PrizeReclaimed {
+            pool: accts.pool.key(),
+            user: pending.user,
+            round_num: pending.round_num,
+            item_indexes: unclaimed.iter().map(|p| p.item_index).collect(),
+            mints: unclaimed.iter().map(|p| p.mint).collect(),
+            amounts: unclaimed.iter().map(|p| p.amount).collect(),
+        });
+
+        for (mint, amount) in pending.unclaimed() {
+            accts.pool.release_liability(&mint, amount);
+        }
+        for liability in &pending.reserved {
+            accts.pool.release_liability(&liability.mint, liability.amount);
+        }
+        accts.user_state.remove_pending_round(pending.round_num);
+
+        Ok(())
+    }
+
+    // Permissionless: pays every recipient with fees accrued in `mint` out of its vault.
+    // remaining_accounts hold one destination per accrual of that mint in pool order,
+    // the wallet itself for SOL or its token account otherwise, then any transfer hook accounts.
+    pub fn sweep_fees<'info>(ctx: Context<'_, '_, '_, 'info, SweepFees<'info>>, mint: Pubkey) -> Result<()> {
+        let accts = ctx.accounts;
+        let pool_key = accts.pool.key();
+        let accruals: Vec<FeeAccrual> = accts.pool.fee_accruals.iter().filter(|a| a.mint == mint).copied().collect();
+        require!(ctx.remaining_accounts.len() >= accruals.len(), SpinError::InvalidPaymentAccount);
+        let (destinations, hook_accounts) = ctx.remaining_accounts.split_at(accruals.len());
+
+        let wheel_id = accts.pool.wheel_id.to_le_bytes();
+        let authority_seeds = &[ESCROW_PDA_SEED.as_bytes(), &wheel_id, &[ctx.bumps.pool]];
+
+        for (accrual, to) in accruals.iter().zip(destinations) {
+            if mint == NATIVE_SOL_MINT {
+                require!(to.key() == accrual.wallet, SpinError::InvalidPaymentAccount);
+                invoke_signed(
+                    &system_instruction::transfer(&accts.vault.key(), &to.key(), accrual.amount),
+                    &[
+                        accts.vault.to_account_info(),
+                        to.clone(),
+                        accts.system_program.to_account_info(),
+                    ],
+                    &[&[VAULT_SEED, pool_key.as_ref(), &[ctx.bumps.vault]]],
+                )?;
+            } else {
+                let (Some(mint_account), Some(reward_vault)) = (&accts.mint, &accts.reward_vault) else {
+                    return err!(SpinError::InvalidPaymentAccount);
+                };
+                require!(
+                    mint_account.key() == mint && reward_vault.key() == fn_reward_vault_address(ctx.program_id, &pool_key, &mint),
+                    SpinError::InvalidRewardVault
+                );
+                let dest = fn_token_account(to, &accts.token_program.key())?;
+                require!(dest.mint == mint && dest.owner == accrual.wallet, SpinError::InvalidPaymentAccount);
+
+                token_interface::transfer_checked(
+                    CpiContext::new(
+                        accts.token_program.to_account_info(),
+                        TransferChecked {
+                            from: reward_vault.to_account_info(),
+                            mint: mint_account.to_account_info(),
+                            to: to.clone(),
+                            authority: accts.pool.to_account_info(),
+                        },
+                    )
+                    .with_signer(&[&authority_seeds[..]])
+                    .with_remaining_accounts(hook_accounts.to_vec()),
+                    accrual.amount,
+                    mint_account.decimals,
                 )?;
             }
 
+            accts.pool.release_liability(&mint, accrual.amount);
         }
+        accts.pool.fee_accruals.retain(|a| a.mint != mint);
+
+        emit!(FeesSwept {
+            pool: pool_key,
+            mint,
+            wallets: accruals.iter().map(|a| a.wallet).collect(),
+            amounts: accruals.iter().map(|a| a.amount).collect(),
+        });
 
         Ok(())
     }
 
-    pub fn withdraw_paid_tokens(
-        ctx : Context<Withdraw>,
+    pub fn withdraw_paid_tokens<'info>(
+        ctx: Context<'_, '_, '_, 'info, Withdraw<'info>>,
         amount: u64,
         ) -> Result<()> {
+        let withdrawable = ctx.accounts.pool.withdrawable(&ctx.accounts.mint.key(), ctx.accounts.source_account.amount);
+        require!(amount <= withdrawable, SpinError::WithdrawExceedsFree);
 
+        let wheel_id = ctx.accounts.pool.wheel_id.to_le_bytes();
         let (_vault_authority, vault_authority_bump) =
-        Pubkey::find_program_address(&[ESCROW_PDA_SEED.as_ref()], ctx.program_id);
-        let authority_seeds = &[&ESCROW_PDA_SEED.as_bytes()[..], &[vault_authority_bump]];
+        Pubkey::find_program_address(&[ESCROW_PDA_SEED.as_bytes(), &wheel_id], ctx.program_id);
+        let authority_seeds = &[ESCROW_PDA_SEED.as_bytes(), &wheel_id, &[vault_authority_bump]];
 
-        token::transfer(
-            ctx.accounts.into_transfer_from_pda_context()
-                .with_signer(&[&authority_seeds[..]]),
+        token_interface::transfer_checked(
+            ctx.accounts.transfer_from_pda_context()
+                .with_signer(&[&authority_seeds[..]])
+                .with_remaining_accounts(ctx.remaining_accounts.to_vec()),
         amount,
+        ctx.accounts.mint.decimals,
         )?;
 
         Ok(())
@@ -289,9 +861,12 @@ pub mod spin_game {
 
     pub fn withdraw_sol( ctx : Context<WithdrawSol>, amount: u64, bump: u8) -> Result<()> {
         let accts = ctx.accounts;
+        let balance = accts.vault.lamports().saturating_sub(Rent::get()?.minimum_balance(0));
+        require!(amount <= accts.pool.withdrawable(&NATIVE_SOL_MINT, balance), SpinError::WithdrawExceedsFree);
 
         // send fee to treasury
         // let bump = ctx.bumps.get("vault").unwrap();
+        let pool_key = accts.pool.key();
         invoke_signed(
             &system_instruction::transfer(&accts.vault.key(), &accts.dest_account.key(), amount),
             &[
@@ -299,14 +874,22 @@ pub mod spin_game {
                 accts.dest_account.clone(),
                 accts.system_program.to_account_info().clone(),
             ],
-            &[&[VAULT_SEED, &[bump]]],
+            &[&[VAULT_SEED, pool_key.as_ref(), &[bump]]],
         )?;
 
         Ok(())
     }
 
+    // forfeits whatever is still unclaimed, or the spin itself if it can't be settled;
+    // the payment is never refunded
     pub fn close_user_pending_acc(ctx : Context<CloseUserPendingAcc>) -> Result<()> {
-        ctx.accounts.user_pendingstate.is_claimed = 1;
+        for (mint, amount) in ctx.accounts.user_pendingstate.unclaimed() {
+            ctx.accounts.pool.release_liability(&mint, amount);
+        }
+        for liability in &ctx.accounts.user_pendingstate.reserved {
+            ctx.accounts.pool.release_liability(&liability.mint, liability.amount);
+        }
+        ctx.accounts.user_state.remove_pending_round(ctx.accounts.user_pendingstate.round_num);
 
         Ok(())
     }
@@ -323,28 +906,29 @@ pub mod spin_game {
 }
 
 #[derive(Accounts)]
+#[instruction(wheel_id: u64)]
 pub struct Initialize<'info> {
     #[account(mut, constraint = initializer.key() == Pubkey::try_from(INITIALIZER_KEY).unwrap())]
     pub initializer: Signer<'info>,
 
-    #[account(init, seeds=[ESCROW_PDA_SEED.as_ref()], bump, payer=initializer, space=size_of::<Pool>() + 8)]
+    #[account(init, seeds=[ESCROW_PDA_SEED.as_bytes(), &wheel_id.to_le_bytes()], bump, payer=initializer, space=size_of::<Pool>() + 8)]
     pub pool : Box<Account<'info, Pool>>,
 
-    #[account(init, seeds=[LAST_USERS_SEED.as_ref()], bump, payer=initializer, space=size_of::<LatestUsers>() + 8)]
+    #[account(init, seeds=[LAST_USERS_SEED.as_ref(), pool.key().as_ref()], bump, payer=initializer, space=size_of::<LatestUsers>() + 8)]
     pub last_users : Box<Account<'info, LatestUsers>>,
 
     /// CHECK: This is not dangerous because we don't read or write from this account
     pub super_admin: AccountInfo<'info>,
 
-    #[account(zero)]
+    #[account(init, seeds=[ITEM_LIST_SEED, pool.key().as_ref()], bump, payer=initializer, space=SpinItemList::space(SPIN_ITEM_COUNT as u8))]
     pub state : AccountLoader<'info, SpinItemList>,
 
-    #[account(init, seeds=[ADMIN_LIST_SEED], bump, payer=initializer, space=size_of::<AdminInfo>() + 8)]
+    #[account(init, seeds=[ADMIN_LIST_SEED, pool.key().as_ref()], bump, payer=initializer, space=size_of::<AdminInfo>() + 8)]
     pub admin_info : Box<Account<'info, AdminInfo>>,
 
     #[account(
         mut,
-        seeds = [VAULT_SEED],
+        seeds = [VAULT_SEED, pool.key().as_ref()],
         bump
     )]
     /// CHECK: this should be checked with address in pool
@@ -362,15 +946,28 @@ pub struct SetPayInfo<'info> {
 
     #[account(
         mut,
-        seeds=[ESCROW_PDA_SEED.as_ref()],
+        seeds=[ESCROW_PDA_SEED.as_bytes(), &pool.wheel_id.to_le_bytes()],
         bump,
     )]
     pub pool : Box<Account<'info, Pool>>,
 
-    #[account(mut)]
+    #[account(mut, seeds=[ADMIN_LIST_SEED, pool.key().as_ref()], bump)]
     pub admin_info : Account<'info, AdminInfo>,
 }
 
+#[derive(Accounts)]
+pub struct ConfigurePool<'info> {
+    pub superadmin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds=[ESCROW_PDA_SEED.as_bytes(), &pool.wheel_id.to_le_bytes()],
+        bump,
+        constraint = pool.superadmin == superadmin.key(),
+    )]
+    pub pool : Box<Account<'info, Pool>>,
+}
+
 #[derive(Accounts)]
 pub struct SpinWheel<'info> {
     /// CHECK: This is not dangerous because we don't read or write from this account
@@ -379,34 +976,81 @@ pub struct SpinWheel<'info> {
 
     #[account(
         mut,
-        seeds=[ESCROW_PDA_SEED.as_ref()],
+        seeds=[ESCROW_PDA_SEED.as_bytes(), &pool.wheel_id.to_le_bytes()],
         bump,
         constraint = pool.superadmin == superadmin.key(),
     )]
     pub pool : Box<Account<'info, Pool>>,
 
+    #[account(mut, constraint = fn_is_item_table(&state.key(), &pool.key()) @ SpinError::InvalidItemTable)]
+    pub state : AccountLoader<'info, SpinItemList>,
+}
+
+// growth per call is capped at MAX_PERMITTED_DATA_INCREASE (10 KiB, ~30 items)
+#[derive(Accounts)]
+#[instruction(capacity: u8)]
+pub struct ResizeItems<'info> {
     #[account(mut)]
+    pub superadmin: Signer<'info>,
+
+    #[account(
+        seeds=[ESCROW_PDA_SEED.as_bytes(), &pool.wheel_id.to_le_bytes()],
+        bump,
+        constraint = pool.superadmin == superadmin.key(),
+    )]
+    pub pool : Box<Account<'info, Pool>>,
+
+    #[account(
+        mut,
+        constraint = fn_is_item_table(&state.key(), &pool.key()) @ SpinError::InvalidItemTable,
+        realloc = SpinItemList::space(capacity),
+        realloc::payer = superadmin,
+        realloc::zero = false,
+    )]
     pub state : AccountLoader<'info, SpinItemList>,
+
+    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-#[instruction(rand: u32, round_id : u64)]
-pub struct PlayGame<'info> {
+pub struct ViewOdds<'info> {
+    #[account(seeds=[ESCROW_PDA_SEED.as_bytes(), &pool.wheel_id.to_le_bytes()], bump)]
+    pub pool : Box<Account<'info, Pool>>,
+
+    #[account(constraint = fn_is_item_table(&state.key(), &pool.key()) @ SpinError::InvalidItemTable)]
+    pub state : AccountLoader<'info, SpinItemList>,
+}
+
+#[derive(Accounts)]
+pub struct ViewPendingRounds<'info> {
+    /// CHECK: only used as a seed
+    pub user: UncheckedAccount<'info>,
+
+    #[account(seeds=[ESCROW_PDA_SEED.as_bytes(), &pool.wheel_id.to_le_bytes()], bump)]
+    pub pool : Box<Account<'info, Pool>>,
+
+    #[account(seeds = [USER_STATE_SEED, pool.key().as_ref(), user.key().as_ref()], bump)]
+    pub user_state: Box<Account<'info, UserState>>,
+}
+
+#[derive(Accounts)]
+#[instruction(payment_mint: Pubkey, commitment: [u8; 32], voucher: Option<Voucher>)]
+pub struct RequestSpin<'info> {
     #[account(mut)]
     pub user: Signer<'info>,
 
-    #[account(mut, seeds=[ESCROW_PDA_SEED.as_ref()], bump)]
+    #[account(mut, seeds=[ESCROW_PDA_SEED.as_bytes(), &pool.wheel_id.to_le_bytes()], bump)]
     pub pool : Box<Account<'info, Pool>>,
 
-    #[account(mut)]
+    #[account(mut, seeds=[LAST_USERS_SEED.as_ref(), pool.key().as_ref()], bump)]
     pub last_users : Box<Account<'info, LatestUsers>>,
 
-    #[account(mut)]
+    #[account(mut, seeds=[ITEM_LIST_SEED, pool.key().as_ref()], bump)]
     pub state : AccountLoader<'info, SpinItemList>,
 
     #[account(
         init_if_needed,
-        seeds = [USER_STATE_SEED, user.key().as_ref()],
+        seeds = [USER_STATE_SEED, pool.key().as_ref(), user.key().as_ref()],
         bump,
         payer = user,
         space = 8 + size_of::<UserState>()
@@ -415,112 +1059,507 @@ pub struct PlayGame<'info> {
 
     #[account(
         init,
-        seeds = [&round_id.to_le_bytes(), user.key().as_ref()],
+        // the round this spin becomes, so clients can derive it from user_state
+        seeds = [&(user_state.round_num + 1).to_le_bytes(), pool.key().as_ref(), user.key().as_ref()],
         bump,
         payer = user,
-        space = 8 + size_of::<UserPendingClaimState>()
+        space = UserPendingClaimState::space(1, 0)
     )]
     pub user_pendingstate: Box<Account<'info, UserPendingClaimState>>,
 
-    // fronk mint
-    #[account(mut, constraint = fronk_mint.key() == Pubkey::try_from(FRONK_MINT).unwrap())]
-    pub fronk_mint: Box<Account<'info, Mint>>,
+    /// CHECK: PRICE_MODE_USD_CENTS only, must be the option's price_feed, parsed in PythPrice::load
+    pub price_feed: Option<UncheckedAccount<'info>>,
 
-    // fronk vault that holds the dust mint for distribution
+    // SPL payments only, checked against the payment mint in request_spin
+    #[account(mut)]
+    pub payment_mint: Option<Box<InterfaceAccount<'info, Mint>>>,
+
+    #[account(mut, constraint = source_account.owner == user.key())]
+    pub source_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    // reward vault PDA of the payment mint
+    #[account(mut)]
+    pub pay_vault: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    // voucher spins only, its creation fails once the voucher has been redeemed
     #[account(
-        mut,
-        constraint = fronk_vault.mint == fronk_mint.key() && fronk_vault.owner == pool.key()
+        init,
+        seeds = [VOUCHER_SEED, pool.key().as_ref(), fn_voucher_issuer(&voucher).as_ref(), &fn_voucher_nonce(&voucher).to_le_bytes()],
+        bump,
+        payer = user,
+        space = 8 + size_of::<UsedVoucher>()
     )]
-    pub fronk_vault: Box<Account<'info, TokenAccount>>,
+    pub used_voucher: Option<Box<Account<'info, UsedVoucher>>>,
+
+    /// CHECK: voucher spins only, address is checked, read by Voucher::verify
+    #[account(address = sysvar::instructions::ID)]
+    pub instructions: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: SOL payments with a burn share only
+    #[account(mut, address = solana_program::incinerator::ID)]
+    pub incinerator: Option<UncheckedAccount<'info>>,
 
-    // fronk dev_wallet that holds the dust mint for distribution
     #[account(
         mut,
-        constraint = fronk_vault.mint == fronk_mint.key() && fronk_vault.owner == dev_account.key()
+        seeds = [VAULT_SEED, pool.key().as_ref()],
+        bump
     )]
-    pub fronk_dev: Box<Account<'info, TokenAccount>>,
+    /// CHECK: this should be checked with address in pool
+    pub vault: AccountInfo<'info>,
+
+    /// CHECK: This is not dangerous because we don't read or write from this account
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+impl<'info> RequestSpin<'info> {
+    fn pay_and_commit(
+        &mut self,
+        program_id: &Pubkey,
+        remaining_accounts: &[AccountInfo<'info>],
+        payment_mint: Pubkey,
+        commitment: [u8; 32],
+        voucher: Option<Voucher>,
+        spin_count: u8,
+    ) -> Result<()> {
+        let (affordable_mask, reservation, vault_count) = fn_snapshot_draw(
+            program_id,
+            &self.pool,
+            &self.state.to_account_info(),
+            &self.vault,
+            remaining_accounts,
+            spin_count,
+        )?;
+        let hook_accounts = &remaining_accounts[vault_count..];
+
+        let option = self.pool.payment_option(&payment_mint).ok_or(SpinError::UnsupportedPaymentMint)?;
+        let listed_price = option.price.checked_mul(spin_count as u64).ok_or(SpinError::RewardAmountOverflow)?;
+        let mut pay_amount = if option.price_mode == PRICE_MODE_USD_CENTS {
+            let feed = self.price_feed.as_ref().ok_or(SpinError::InvalidPriceFeed)?;
+            require!(feed.key() == option.price_feed, SpinError::InvalidPriceFeed);
+            let decimals = match &self.payment_mint {
+                _ if payment_mint == NATIVE_SOL_MINT => 9,
+                Some(mint) => mint.decimals,
+                None => return err!(SpinError::InvalidPaymentAccount),
+            };
+
+            let price = PythPrice::load(feed)?;
+            price.check(&self.pool, Clock::get()?.slot)?;
+            price.cents_to_amount(listed_price, decimals)?
+        } else {
+            listed_price
+        };
+        if self.pool.bulk_discount_min > 0 && spin_count >= self.pool.bulk_discount_min {
+            pay_amount -= fn_fee_share(pay_amount, self.pool.bulk_discount_bps);
+        }
+
+        // the record's init already failed if this (issuer, nonce) was redeemed before
+        match (&voucher, &mut self.used_voucher, &self.instructions) {
+            (Some(voucher), Some(used_voucher), Some(instructions)) => {
+                voucher.verify(instructions, &self.pool, &self.user.key())?;
+                // only the spins the voucher was signed for are discounted
+                let covered = voucher.spin_count.min(spin_count) as u128;
+                let covered_price = (pay_amount as u128 * covered / spin_count as u128) as u64;
+                pay_amount -= fn_fee_share(covered_price, voucher.discount_bps);
+
+                used_voucher.pool = self.pool.key();
+                used_voucher.issuer = voucher.issuer;
+                used_voucher.nonce = voucher.nonce;
+                used_voucher.user = self.user.key();
+                used_voucher.redeemed_at = Clock::get()?.unix_timestamp;
+                emit!(VoucherRedeemed {
+                    pool: self.pool.key(),
+                    user: self.user.key(),
+                    issuer: voucher.issuer,
+                    nonce: voucher.nonce,
+                    discount_bps: voucher.discount_bps,
+                    spin_count: voucher.spin_count,
+                });
+            }
+            (None, None, _) => {}
+            _ => return err!(SpinError::InvalidVoucher),
+        }
+
+        // burn shares are destroyed right away, everything else lands in one vault
+        let burn_bps: u16 = self.pool.fee_schedule().iter().filter(|r| r.burn).map(|r| r.bps).sum();
+        let burned = fn_fee_share(pay_amount, burn_bps);
+        let deposit = pay_amount - burned;
+
+        let vault_received = if pay_amount == 0 {
+            0
+        } else if payment_mint == NATIVE_SOL_MINT {
+            let mut legs = vec![(self.vault.to_account_info(), deposit)];
+            if burned > 0 {
+                let incinerator = self.incinerator.as_ref().ok_or(SpinError::InvalidPaymentAccount)?;
+                legs.push((incinerator.to_account_info(), burned));
+            }
+            for (to, amount) in legs {
+                invoke(
+                    &system_instruction::transfer(&self.user.key(), &to.key(), amount),
+                    &[
+                        self.user.to_account_info(),
+                        to,
+                        self.system_program.to_account_info(),
+                    ],
+                )?;
+            }
+            deposit
+        } else {
+            let (Some(mint), Some(source), Some(pay_vault)) = (
+                &self.payment_mint,
+                &self.source_account,
+                &self.pay_vault,
+            ) else {
+                return err!(SpinError::InvalidPaymentAccount);
+            };
+            // the mint's reward vault, where prizes, fees and withdrawals draw from
+            require!(
+                mint.key() == payment_mint
+                    && pay_vault.key() == fn_reward_vault_address(program_id, &self.pool.key(), &payment_mint),
+                SpinError::InvalidPaymentAccount
+            );
+
+            if burned > 0 {
+                token_interface::burn(CpiContext::new(
+                    self.token_program.to_account_info(),
+                    Burn {
+                        mint: mint.to_account_info(),
+                        from: source.to_account_info(),
+                        authority: self.user.to_account_info(),
+                    },
+                ), burned)?;
+            }
+
+            // pay, forwarding any Token-2022 transfer hook accounts after the reward vaults
+            token_interface::transfer_checked(CpiContext::new(
+                self.token_program.to_account_info(),
+                TransferChecked {
+                    from: source.to_account_info(),
+                    mint: mint.to_account_info(),
+                    to: pay_vault.to_account_info(),
+                    authority: self.user.to_account_info(),
+                },
+            ).with_remaining_accounts(hook_accounts.to_vec()), deposit, mint.decimals)?;
+
+            // Token-2022 transfer fees come out of what the vault receives
+            deposit - fn_transfer_fee(&mint.to_account_info(), deposit)?
+        };
+        msg!("transfer success, vault received {}", vault_received);
+
+        // recipients share any transfer fee in proportion, so accruals never exceed the deposit
+        let schedule = self.pool.fee_schedule().to_vec();
+        for recipient in schedule.iter().filter(|r| !r.burn) {
+            let share = fn_fee_share(pay_amount, recipient.bps) as u128 * vault_received as u128 / deposit.max(1) as u128;
+            self.pool.accrue_fee(recipient.wallet, payment_mint, share as u64)?;
+        }
+
+        if burned > 0 {
+            let pool_key = self.pool.key();
+            let option = self.pool.payment_option_mut(&payment_mint).ok_or(SpinError::UnsupportedPaymentMint)?;
+            option.total_burned = option.total_burned.saturating_add(burned);
+            emit!(SpinBurned {
+                pool: pool_key,
+                user: self.user.key(),
+                mint: payment_mint,
+                amount: burned,
+                total_burned: option.total_burned,
+            });
+        }
+
+        let round_num = self.user_state.begin_round(self.user.key())?;
+
+        // commit to hash(secret) and a slot whose hash nobody knows yet
+        self.user_pendingstate.user = self.user.key();
+        self.user_pendingstate.pool = self.pool.key();
+        self.user_pendingstate.round_num = round_num;
+        self.user_pendingstate.commitment = commitment;
+        self.user_pendingstate.reveal_slot = Clock::get()?.slot + SPIN_REVEAL_DELAY_SLOTS;
+        self.user_pendingstate.pay_mint = payment_mint;
+        self.user_pendingstate.pay_amount = pay_amount;
+        self.user_pendingstate.item_table = self.state.key();
+        self.user_pendingstate.affordable_mask = affordable_mask;
+        self.user_pendingstate.randomness_source = self.pool.randomness_source;
+        self.user_pendingstate.oracle = self.pool.oracle;
+        self.user_pendingstate.settle_deadline = fn_settle_deadline(self.pool.randomness_source, self.user_pendingstate.reveal_slot);
+        self.user_pendingstate.spin_count = spin_count;
+
+        // the worst case of every draw stays out of withdrawals until settle_spin
+        for liability in &reservation {
+            self.pool.add_liability(liability.mint, liability.amount)?;
+        }
+        self.user_pendingstate.reserved = reservation;
+
+        // the pending account is created for one draw and no reservation, and grows
+        // to hold both; the pool grows when a new mint becomes owed
+        let space = UserPendingClaimState::space(spin_count, self.user_pendingstate.reserved.len());
+        let user = self.user.to_account_info();
+        let system_program = self.system_program.to_account_info();
+        fn_grow_account(&self.user_pendingstate.to_account_info(), &user, &system_program, space)?;
+        fn_grow_account(&self.pool.to_account_info(), &user, &system_program, self.pool.space())
+    }
+}
+
+#[derive(Accounts)]
+pub struct FreeSpin<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(mut, seeds=[ESCROW_PDA_SEED.as_bytes(), &pool.wheel_id.to_le_bytes()], bump)]
+    pub pool : Box<Account<'info, Pool>>,
 
-    // fronk burn_wallet that holds the dust mint for distribution
     #[account(
-        mut,
-        constraint = fronk_vault.mint == fronk_mint.key() && fronk_vault.owner == burn_account.key()
+        seeds=[if pool.free_spin_table { FREE_ITEM_LIST_SEED } else { ITEM_LIST_SEED }, pool.key().as_ref()],
+        bump,
     )]
-    pub fronk_burn: Box<Account<'info, TokenAccount>>,
+    pub state : AccountLoader<'info, SpinItemList>,
 
-    // source account
-    #[account(mut, constraint = source_account.owner == user.key() && source_account.mint == fronk_mint.key())]
-    /// CHECK: this should be checked with address in pool
-    pub source_account: Box<Account<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        seeds = [USER_STATE_SEED, pool.key().as_ref(), user.key().as_ref()],
+        bump,
+        payer = user,
+        space = 8 + size_of::<UserState>()
+    )]
+    pub user_state: Box<Account<'info, UserState>>,
+
+    #[account(
+        init,
+        seeds = [&(user_state.round_num + 1).to_le_bytes(), pool.key().as_ref(), user.key().as_ref()],
+        bump,
+        payer = user,
+        space = UserPendingClaimState::space(1, 0)
+    )]
+    pub user_pendingstate: Box<Account<'info, UserPendingClaimState>>,
+
+    #[account(
+        seeds = [VAULT_SEED, pool.key().as_ref()],
+        bump
+    )]
+    /// CHECK: the pool's SOL vault, checked by seeds
+    pub vault: AccountInfo<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+// remaining_accounts: the pool-owned reward vault of every SPL item in the table
+#[derive(Accounts)]
+pub struct SettleSpin<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(mut, seeds=[ESCROW_PDA_SEED.as_bytes(), &pool.wheel_id.to_le_bytes()], bump)]
+    pub pool : Box<Account<'info, Pool>>,
+
+    #[account(mut, seeds=[LAST_USERS_SEED.as_ref(), pool.key().as_ref()], bump)]
+    pub last_users : Box<Account<'info, LatestUsers>>,
+
+    #[account(mut, constraint = state.key() == user_pendingstate.item_table @ SpinError::InvalidItemTable)]
+    pub state : AccountLoader<'info, SpinItemList>,
 
     #[account(
         mut,
-        seeds = [VAULT_SEED],
+        constraint = user_pendingstate.user == user.key() && user_pendingstate.pool == pool.key()
+    )]
+    pub user_pendingstate: Box<Account<'info, UserPendingClaimState>>,
+
+    #[account(mut, seeds = [USER_STATE_SEED, pool.key().as_ref(), user.key().as_ref()], bump)]
+    pub user_state: Box<Account<'info, UserState>>,
+
+    #[account(
+        seeds = [VAULT_SEED, pool.key().as_ref()],
         bump
     )]
     /// CHECK: this should be checked with address in pool
     pub vault: AccountInfo<'info>,
 
-    /// CHECK: this should be checked with sol vault address
-    #[account(mut, constraint = dev_account.key() == pool.dev_wallet)]
-    pub dev_account: AccountInfo<'info>,
-    /// CHECK: this should be checked with sol vault address
-    #[account(constraint = burn_account.key() == pool.burn_wallet)]
-    pub burn_account: AccountInfo<'info>,
+    /// CHECK: address is checked, entries are parsed in SlotHashesSource
+    #[account(address = sysvar::slot_hashes::ID)]
+    pub slot_hashes: AccountInfo<'info>,
+
+    /// CHECK: address is checked, read by OracleSource
+    #[account(address = sysvar::instructions::ID)]
+    pub instructions: AccountInfo<'info>,
 
-    /// CHECK: This is not dangerous because we don't read or write from this account
-    pub associated_token_program: Program<'info, AssociatedToken>,
     pub system_program: Program<'info, System>,
-    pub token_program: Program<'info, Token>,
-    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct InitRewardVault<'info> {
+    #[account(mut)]
+    pub superadmin: Signer<'info>,
+
+    #[account(
+        seeds=[ESCROW_PDA_SEED.as_bytes(), &pool.wheel_id.to_le_bytes()],
+        bump,
+        constraint = pool.superadmin == superadmin.key(),
+    )]
+    pub pool : Box<Account<'info, Pool>>,
 
-    /// CHECK: We're reading data from this chainlink feed account
-    pub pyth_account: AccountInfo<'info>,
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        init,
+        payer = superadmin,
+        seeds = [REWARD_VAULT_SEED, pool.key().as_ref(), mint.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = pool,
+        token::token_program = token_program,
+    )]
+    pub reward_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
 }
 
 #[derive(Accounts)]
-pub struct Claim<'info> {
-    /// CHECK: This is not dangerous because we don't read or write from this account
-    #[account(mut, signer)]
-    pub owner : AccountInfo<'info>,
+pub struct InitFreeItems<'info> {
+    #[account(mut)]
+    pub superadmin: Signer<'info>,
 
-    /// CHECK: This is not dangerous because we don't read or write from this account
-    #[account(mut, seeds=[ESCROW_PDA_SEED.as_ref()], bump)]
+    #[account(
+        seeds=[ESCROW_PDA_SEED.as_bytes(), &pool.wheel_id.to_le_bytes()],
+        bump,
+        constraint = pool.superadmin == superadmin.key(),
+    )]
+    pub pool : Box<Account<'info, Pool>>,
+
+    #[account(init, seeds=[FREE_ITEM_LIST_SEED, pool.key().as_ref()], bump, payer=superadmin, space=SpinItemList::space(SPIN_ITEM_COUNT as u8))]
+    pub state : AccountLoader<'info, SpinItemList>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DepositNft<'info> {
+    #[account(mut)]
+    pub superadmin: Signer<'info>,
+
+    #[account(
+        seeds=[ESCROW_PDA_SEED.as_bytes(), &pool.wheel_id.to_le_bytes()],
+        bump,
+        constraint = pool.superadmin == superadmin.key(),
+    )]
+    pub pool : Box<Account<'info, Pool>>,
+
+    #[account(mut, constraint = fn_is_item_table(&state.key(), &pool.key()) @ SpinError::InvalidItemTable)]
+    pub state : AccountLoader<'info, SpinItemList>,
+
+    #[account(constraint = nft_mint.decimals == 0 @ SpinError::InvalidNft)]
+    pub nft_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut, constraint = source_account.owner == superadmin.key() && source_account.mint == nft_mint.key())]
+    pub source_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // per-NFT escrow owned by the pool authority, also read as its reward vault in settle_spin
+    #[account(
+        init_if_needed,
+        payer = superadmin,
+        seeds = [REWARD_VAULT_SEED, pool.key().as_ref(), nft_mint.key().as_ref()],
+        bump,
+        token::mint = nft_mint,
+        token::authority = pool,
+        token::token_program = token_program,
+    )]
+    pub escrow_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: only read for items with a collection, checked in fn_check_collection
+    pub metadata: Option<UncheckedAccount<'info>>,
+
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimSol<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(mut, seeds=[ESCROW_PDA_SEED.as_bytes(), &pool.wheel_id.to_le_bytes()], bump)]
     pub pool : Box<Account<'info, Pool>>,
 
     #[account(
         mut,
-        seeds = [VAULT_SEED],
+        seeds = [VAULT_SEED, pool.key().as_ref()],
         bump
     )]
-    /// CHECK: this should be checked with address in pool
+    /// CHECK: the pool's SOL vault, checked by seeds
     pub vault: AccountInfo<'info>,
 
     #[account(
         mut,
-        constraint = owner.key() == user_pendingstate.user
+        constraint = owner.key() == user_pendingstate.user && user_pendingstate.pool == pool.key()
     )]
     pub user_pendingstate: Box<Account<'info, UserPendingClaimState>>,
 
+    #[account(mut, seeds = [USER_STATE_SEED, pool.key().as_ref(), owner.key().as_ref()], bump)]
+    pub user_state: Box<Account<'info, UserState>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct Claim<'info> {
     /// CHECK: This is not dangerous because we don't read or write from this account
-    #[account(mut)]
-    pub source_reward_account: Box<Account<'info, TokenAccount>>,
+    #[account(mut, signer)]
+    pub owner : AccountInfo<'info>,
 
     /// CHECK: This is not dangerous because we don't read or write from this account
-    #[account(mut)]
-    pub dest_reward_account: Box<Account<'info, TokenAccount>>,
+    #[account(mut, seeds=[ESCROW_PDA_SEED.as_bytes(), &pool.wheel_id.to_le_bytes()], bump)]
+    pub pool : Box<Account<'info, Pool>>,
+
+    #[account(
+        mut,
+        constraint = owner.key() == user_pendingstate.user && user_pendingstate.pool == pool.key()
+    )]
+    pub user_pendingstate: Box<Account<'info, UserPendingClaimState>>,
+
+    #[account(mut, seeds = [USER_STATE_SEED, pool.key().as_ref(), owner.key().as_ref()], bump)]
+    pub user_state: Box<Account<'info, UserState>>,
+
+    // the pool's reward vault for a mint this pending account still owes
+    #[account(
+        mut,
+        seeds = [REWARD_VAULT_SEED, pool.key().as_ref(), reward_mint.key().as_ref()],
+        bump,
+        token::mint = reward_mint,
+        token::authority = pool,
+        constraint = user_pendingstate.next_unclaimed(&reward_mint.key()).is_some() @ SpinError::InvalidReward,
+    )]
+    pub source_reward_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // created for first-time winners of a mint, rent paid by the winner
+    #[account(
+        init_if_needed,
+        payer = owner,
+        associated_token::mint = reward_mint,
+        associated_token::authority = owner,
+        associated_token::token_program = token_program,
+        constraint = dest_reward_account.owner == owner.key(),
+    )]
+    pub dest_reward_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub reward_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// CHECK: only read for NFT prizes with a collection, checked in fn_check_collection
+    pub metadata: Option<UncheckedAccount<'info>>,
 
     /// CHECK: This is not dangerous because we don't read or write from this account
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
     pub system_program: Program<'info, System>,
 }
 
 impl<'info> Claim<'info> {
-    fn into_transfer_to_pda_context(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
-        let cpi_accounts = Transfer {
+    fn transfer_from_pda_context(&self) -> CpiContext<'_, '_, '_, 'info, TransferChecked<'info>> {
+        let cpi_accounts = TransferChecked {
             from: self
                 .source_reward_account
                 .to_account_info()
                 .clone(),
+            mint: self.reward_mint.to_account_info().clone(),
             to: self.dest_reward_account.to_account_info().clone(),
             authority: self.pool.to_account_info().clone(),
         };
@@ -528,6 +1567,81 @@ impl<'info> Claim<'info> {
     }
 }
 
+#[derive(Accounts)]
+pub struct ClaimAll<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(mut, seeds=[ESCROW_PDA_SEED.as_bytes(), &pool.wheel_id.to_le_bytes()], bump)]
+    pub pool: Box<Account<'info, Pool>>,
+
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, pool.key().as_ref()],
+        bump
+    )]
+    /// CHECK: the pool's SOL vault, checked by seeds
+    pub vault: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        constraint = owner.key() == user_pendingstate.user && user_pendingstate.pool == pool.key()
+    )]
+    pub user_pendingstate: Box<Account<'info, UserPendingClaimState>>,
+
+    #[account(mut, seeds = [USER_STATE_SEED, pool.key().as_ref(), owner.key().as_ref()], bump)]
+    pub user_state: Box<Account<'info, UserState>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ReclaimExpired<'info> {
+    #[account(mut, seeds=[ESCROW_PDA_SEED.as_bytes(), &pool.wheel_id.to_le_bytes()], bump)]
+    pub pool : Box<Account<'info, Pool>>,
+
+    #[account(mut, constraint = state.key() == user_pendingstate.item_table @ SpinError::InvalidItemTable)]
+    pub state : AccountLoader<'info, SpinItemList>,
+
+    /// CHECK: receives the pending account rent, checked against user_pendingstate
+    #[account(mut, constraint = user.key() == user_pendingstate.user)]
+    pub user: UncheckedAccount<'info>,
+
+    #[account(mut, seeds = [USER_STATE_SEED, pool.key().as_ref(), user.key().as_ref()], bump)]
+    pub user_state: Box<Account<'info, UserState>>,
+
+    #[account(
+        mut,
+        close = user,
+        constraint = user_pendingstate.pool == pool.key(),
+    )]
+    pub user_pendingstate: Box<Account<'info, UserPendingClaimState>>,
+}
+
+#[derive(Accounts)]
+pub struct SweepFees<'info> {
+    #[account(mut, seeds=[ESCROW_PDA_SEED.as_bytes(), &pool.wheel_id.to_le_bytes()], bump)]
+    pub pool : Box<Account<'info, Pool>>,
+
+    #[account(
+        mut,
+        seeds = [VAULT_SEED, pool.key().as_ref()],
+        bump
+    )]
+    /// CHECK: the pool's SOL vault, checked by seeds
+    pub vault: AccountInfo<'info>,
+
+    // SPL sweeps only, checked against the swept mint
+    pub mint: Option<Box<InterfaceAccount<'info, Mint>>>,
+    #[account(mut)]
+    pub reward_vault: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
 #[derive(Accounts)]
 pub struct CloseUserPendingAcc<'info> {
     /// CHECK: This is not dangerous because we don't read or write from this account
@@ -537,8 +1651,20 @@ pub struct CloseUserPendingAcc<'info> {
     #[account(
         mut,
         close = owner,
+        constraint = owner.key() == user_pendingstate.user,
     )]
     pub user_pendingstate: Account<'info, UserPendingClaimState>,
+
+    #[account(mut, seeds = [USER_STATE_SEED, user_pendingstate.pool.as_ref(), owner.key().as_ref()], bump)]
+    pub user_state: Account<'info, UserState>,
+
+    #[account(
+        mut,
+        seeds=[ESCROW_PDA_SEED.as_bytes(), &pool.wheel_id.to_le_bytes()],
+        bump,
+        constraint = pool.key() == user_pendingstate.pool,
+    )]
+    pub pool : Box<Account<'info, Pool>>,
 }
 
 
@@ -548,28 +1674,36 @@ pub struct Withdraw<'info> {
     pub authority: Signer<'info>,
 
     /// CHECK: This is not dangerous because we don't read or write from this account
-    #[account(mut, constraint = pool.superadmin == *authority.key)]
+    #[account(mut, seeds=[ESCROW_PDA_SEED.as_bytes(), &pool.wheel_id.to_le_bytes()], bump, constraint = pool.superadmin == *authority.key)]
     pub pool : Box<Account<'info, Pool>>,
 
-    /// CHECK: This is not dangerous because we don't read or write from this account
-    #[account(mut,owner=spl_token::id())]
-    pub source_account : AccountInfo<'info>,
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
 
-    /// CHECK: This is not dangerous because we don't read or write from this account
-    #[account(mut,owner=spl_token::id())]
-    pub dest_account : AccountInfo<'info>,
+    // the mint's reward vault, the account its liabilities are held against
+    #[account(
+        mut,
+        seeds = [REWARD_VAULT_SEED, pool.key().as_ref(), mint.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = pool,
+    )]
+    pub source_account : Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut, constraint = dest_account.mint == mint.key())]
+    pub dest_account : Box<InterfaceAccount<'info, TokenAccount>>,
 
     /// CHECK: This is not dangerous because we don't read or write from this account
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
 }
 
 impl<'info> Withdraw<'info> {
-    fn into_transfer_from_pda_context(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
-        let cpi_accounts = Transfer {
+    fn transfer_from_pda_context(&self) -> CpiContext<'_, '_, '_, 'info, TransferChecked<'info>> {
+        let cpi_accounts = TransferChecked {
             from: self
                 .source_account
                 .to_account_info()
                 .clone(),
+            mint: self.mint.to_account_info().clone(),
             to: self.dest_account.to_account_info().clone(),
             authority: self.pool.to_account_info().clone(),
         };
@@ -584,13 +1718,13 @@ pub struct WithdrawSol<'info> {
 
     #[account(
         mut,
-        seeds = [VAULT_SEED],
+        seeds = [VAULT_SEED, pool.key().as_ref()],
         bump
     )]
     /// CHECK: this should be checked with address in pool
     pub vault: AccountInfo<'info>,
 
-    #[account(constraint = pool.superadmin == *user.key)]
+    #[account(seeds=[ESCROW_PDA_SEED.as_bytes(), &pool.wheel_id.to_le_bytes()], bump, constraint = pool.superadmin == *user.key)]
     pub pool : Box<Account<'info, Pool>>,
 
     #[account(mut)]
@@ -606,10 +1740,10 @@ pub struct ManageAdmin<'info> {
     pub authority: Signer<'info>,
 
     /// CHECK: This is not dangerous because we don't read or write from this account
-    #[account(constraint = pool.superadmin == *authority.key)]
+    #[account(seeds=[ESCROW_PDA_SEED.as_bytes(), &pool.wheel_id.to_le_bytes()], bump, constraint = pool.superadmin == *authority.key)]
     pub pool : Box<Account<'info, Pool>>,
 
-    #[account(mut)]
+    #[account(mut, seeds=[ADMIN_LIST_SEED, pool.key().as_ref()], bump)]
     pub admin_info : Account<'info, AdminInfo>,
 
     /// CHECK: This is not dangerous because we don't read or write from this account
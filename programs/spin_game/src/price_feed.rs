@@ -0,0 +1,163 @@
+use anchor_lang::prelude::*;
+use pyth_client::{AccountType, PriceStatus, MAGIC, VERSION_2};
+
+use crate::account::*;
+use crate::constants::*;
+use crate::errors::*;
+
+// aggregate price of a Pyth v2 price account, read at fixed offsets instead of
+// pyth_client::cast so a malformed account can't produce an invalid enum
+pub struct PythPrice {
+    pub price: i64,
+    pub conf: u64,
+    pub expo: i32,
+    pub status: u32,
+    pub pub_slot: u64,
+}
+
+impl PythPrice {
+    // magic u32 | ver u32 | atype u32 | size u32 | ptype u32 | expo i32 | ... | agg at 208:
+    // price i64 | conf u64 | status u32 | corp_act u32 | pub_slot u64
+    pub fn load(feed: &AccountInfo) -> Result<PythPrice> {
+        require!(*feed.owner == PYTH_PROGRAM_ID, SpinError::InvalidPriceFeed);
+
+        let data = feed.try_borrow_data()?;
+        require!(data.len() >= 240, SpinError::InvalidPriceFeed);
+        let read_u32 = |at: usize| u32::from_le_bytes(data[at..at + 4].try_into().unwrap());
+        let read_u64 = |at: usize| u64::from_le_bytes(data[at..at + 8].try_into().unwrap());
+
+        require!(
+            read_u32(0) == MAGIC && read_u32(4) == VERSION_2 && read_u32(8) == AccountType::Price as u32,
+            SpinError::InvalidPriceFeed
+        );
+
+        Ok(PythPrice {
+            expo: read_u32(20) as i32,
+            price: read_u64(208) as i64,
+            conf: read_u64(216),
+            status: read_u32(224),
+            pub_slot: read_u64(232),
+        })
+    }
+
+    // rejects halted, stale and low-confidence prices
+    pub fn check(&self, pool: &Pool, slot: u64) -> Result<()> {
+        require!(self.status == PriceStatus::Trading as u32, SpinError::PriceNotTrading);
+        require!(self.price > 0, SpinError::InvalidPriceFeed);
+        require!(slot.saturating_sub(self.pub_slot) <= pool.max_price_age_slots, SpinError::StalePrice);
+        require!(
+            self.conf as u128 * BPS_DENOMINATOR as u128 <= self.price as u128 * pool.max_price_conf_bps as u128,
+            SpinError::PriceConfidenceTooWide
+        );
+        Ok(())
+    }
+
+    // base units of a token with `decimals` worth `usd_cents`, rounded up
+    pub fn cents_to_amount(&self, usd_cents: u64, decimals: u8) -> Result<u64> {
+        let pow10 = |exp: u32| 10_u128.checked_pow(exp).ok_or(SpinError::RewardAmountOverflow);
+
+        // amount = cents * 10^decimals / (100 * price * 10^expo)
+        let mut numerator = (usd_cents as u128).checked_mul(pow10(decimals as u32)?).ok_or(SpinError::RewardAmountOverflow)?;
+        let mut denominator = 100 * self.price as u128;
+        if self.expo < 0 {
+            numerator = numerator.checked_mul(pow10(self.expo.unsigned_abs())?).ok_or(SpinError::RewardAmountOverflow)?;
+        } else {
+            denominator = denominator.checked_mul(pow10(self.expo as u32)?).ok_or(SpinError::RewardAmountOverflow)?;
+        }
+
+        let amount = numerator.div_ceil(denominator);
+        Ok(u64::try_from(amount).map_err(|_| SpinError::RewardAmountOverflow)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::{engine::general_purpose::STANDARD, Engine};
+
+    // the account loaded into the test validator, see Anchor.toml
+    const FIXTURE: &str = include_str!("../../../tests/fixtures/pyth_sol_usd.json");
+
+    fn fixture_data() -> Vec<u8> {
+        let data = FIXTURE.split("\"data\": [").nth(1).unwrap();
+        let encoded = data.split('"').nth(1).unwrap();
+        STANDARD.decode(encoded).unwrap()
+    }
+
+    fn load(mut data: Vec<u8>, owner: Pubkey) -> Result<PythPrice> {
+        let key = Pubkey::new_unique();
+        let mut lamports = 0;
+        let feed = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &owner, false, 0);
+        PythPrice::load(&feed)
+    }
+
+    fn pool() -> Pool {
+        Pool {
+            max_price_age_slots: DEFAULT_MAX_PRICE_AGE_SLOTS,
+            max_price_conf_bps: DEFAULT_MAX_PRICE_CONF_BPS,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn parses_fixture() {
+        let price = load(fixture_data(), PYTH_PROGRAM_ID).unwrap();
+        assert_eq!(price.price, 15_000_000_000);
+        assert_eq!(price.conf, 5_000_000);
+        assert_eq!(price.expo, -8);
+        assert_eq!(price.status, PriceStatus::Trading as u32);
+        assert_eq!(price.pub_slot, 0);
+    }
+
+    #[test]
+    fn rejects_foreign_or_short_accounts() {
+        assert_eq!(load(fixture_data(), Pubkey::new_unique()).err(), Some(SpinError::InvalidPriceFeed.into()));
+        assert_eq!(load(fixture_data()[..200].to_vec(), PYTH_PROGRAM_ID).err(), Some(SpinError::InvalidPriceFeed.into()));
+
+        let mut data = fixture_data();
+        data[0] ^= 1;
+        assert_eq!(load(data, PYTH_PROGRAM_ID).err(), Some(SpinError::InvalidPriceFeed.into()));
+    }
+
+    #[test]
+    fn rejects_stale_price() {
+        let price = load(fixture_data(), PYTH_PROGRAM_ID).unwrap();
+        assert!(price.check(&pool(), DEFAULT_MAX_PRICE_AGE_SLOTS).is_ok());
+        assert_eq!(
+            price.check(&pool(), DEFAULT_MAX_PRICE_AGE_SLOTS + 1).err(),
+            Some(SpinError::StalePrice.into())
+        );
+    }
+
+    #[test]
+    fn rejects_wide_confidence() {
+        // 150.00 +- 0.05 is 3.3 bps
+        let price = load(fixture_data(), PYTH_PROGRAM_ID).unwrap();
+        assert!(price.check(&Pool { max_price_conf_bps: 4, ..pool() }, 0).is_ok());
+        assert_eq!(
+            price.check(&Pool { max_price_conf_bps: 3, ..pool() }, 0).err(),
+            Some(SpinError::PriceConfidenceTooWide.into())
+        );
+    }
+
+    #[test]
+    fn rejects_halted_price() {
+        let mut data = fixture_data();
+        data[224..228].copy_from_slice(&(PriceStatus::Halted as u32).to_le_bytes());
+        let price = load(data, PYTH_PROGRAM_ID).unwrap();
+        assert_eq!(price.check(&pool(), 0).err(), Some(SpinError::PriceNotTrading.into()));
+    }
+
+    #[test]
+    fn converts_cents_rounding_up() {
+        let price = load(fixture_data(), PYTH_PROGRAM_ID).unwrap();
+        // $150.00 is exactly one SOL
+        assert_eq!(price.cents_to_amount(15_000, 9).unwrap(), 1_000_000_000);
+        // $1.00 is 6_666_666.67 lamports
+        assert_eq!(price.cents_to_amount(100, 9).unwrap(), 6_666_667);
+        // never rounds a nonzero price down to free
+        assert_eq!(price.cents_to_amount(1, 0).unwrap(), 1);
+        assert_eq!(price.cents_to_amount(0, 9).unwrap(), 0);
+        assert!(price.cents_to_amount(u64::MAX, 30).is_err());
+    }
+}
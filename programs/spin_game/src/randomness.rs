@@ -0,0 +1,196 @@
+use anchor_lang::prelude::*;
+use solana_program::ed25519_program;
+use solana_program::sysvar::instructions::{load_current_index_checked, load_instruction_at_checked};
+
+use crate::constants::*;
+use crate::errors::*;
+
+// entropy mixed with the revealed secret in settle_spin
+pub trait RandomnessSource {
+    fn entropy(&self) -> Result<Entropy>;
+}
+
+pub struct Entropy {
+    pub value: [u8; 32],
+    // slot whose hash was used, 0 for sources that don't read SlotHashes
+    pub slot: u64,
+    // oracle signature `value` hashes, empty for other sources
+    pub signature: Vec<u8>,
+}
+
+// hash of the first slot at or after `slot`, which was still in the future when
+// the player committed. Skipped slots have no entry, so the next produced one is used.
+pub struct SlotHashesSource<'a, 'info> {
+    pub slot_hashes: &'a AccountInfo<'info>,
+    pub slot: u64,
+}
+
+impl<'a, 'info> RandomnessSource for SlotHashesSource<'a, 'info> {
+    // SlotHashes layout: u64 length, then (u64 slot, [u8; 32] hash) entries, newest first
+    fn entropy(&self) -> Result<Entropy> {
+        require!(Clock::get()?.slot > self.slot, SpinError::RevealTooEarly);
+
+        let data = self.slot_hashes.try_borrow_data()?;
+        require!(data.len() >= 8, SpinError::SlotHashUnavailable);
+
+        let len = u64::from_le_bytes(data[..8].try_into().unwrap()) as usize;
+        fn_oldest_slot_hash(&data[8..], len, self.slot).ok_or(error!(SpinError::SlotHashUnavailable))
+    }
+}
+
+// Oldest entry with slot >= `slot`. Only trusted when it is `slot` itself or an
+// older entry is still listed; otherwise the real first entry may have aged out
+// and a later one would stand in for it.
+pub fn fn_oldest_slot_hash(entries: &[u8], len: usize, slot: u64) -> Option<Entropy> {
+    let mut found = None;
+    for entry in entries.chunks_exact(40).take(len) {
+        let entry_slot = u64::from_le_bytes(entry[..8].try_into().unwrap());
+        if entry_slot < slot {
+            return found;
+        }
+        found = Some(Entropy { value: entry[8..].try_into().unwrap(), slot: entry_slot, signature: vec![] });
+        if entry_slot == slot {
+            return found;
+        }
+    }
+    None
+}
+
+// last slot a spin revealed at `reveal_slot` can settle in, 0 when it never runs out.
+// Up to then SlotHashes still lists `reveal_slot` or an older slot, so
+// fn_oldest_slot_hash can tell which entry came first.
+pub fn fn_settle_deadline(randomness_source: u8, reveal_slot: u64) -> u64 {
+    if randomness_source == RANDOMNESS_SOURCE_SLOT_HASHES {
+        reveal_slot + SLOT_HASHES_WINDOW
+    } else {
+        0
+    }
+}
+
+// hash of the oracle's signature over the request, checked through the ed25519 precompile.
+// Ed25519 signing is deterministic, so the oracle has exactly one answer per request.
+// The instruction right before settle_spin must verify one signature over
+// `request (32)` with all offsets pointing into its own data.
+pub struct OracleSource<'a, 'info> {
+    pub instructions: &'a AccountInfo<'info>,
+    pub oracle: Pubkey,
+    pub request: Pubkey,
+}
+
+impl<'a, 'info> RandomnessSource for OracleSource<'a, 'info> {
+    fn entropy(&self) -> Result<Entropy> {
+        let current = load_current_index_checked(self.instructions)?;
+        require!(current > 0, SpinError::InvalidOracleSignature);
+
+        let ix = load_instruction_at_checked(current as usize - 1, self.instructions)?;
+        require!(ix.program_id == ed25519_program::ID, SpinError::InvalidOracleSignature);
+
+        let (signature, pubkey, message) = fn_ed25519_single(&ix.data)?;
+        require!(pubkey == self.oracle.as_ref(), SpinError::InvalidOracleSignature);
+        require!(message == self.request.as_ref(), SpinError::InvalidOracleSignature);
+
+        Ok(Entropy { value: solana_program::hash::hash(signature).to_bytes(), slot: 0, signature: signature.to_vec() })
+    }
+}
+
+// deterministic entropy so integration tests can predict the winning item
+#[cfg(feature = "test-rng")]
+pub struct SeededSource {
+    pub seed: u64,
+}
+
+#[cfg(feature = "test-rng")]
+impl RandomnessSource for SeededSource {
+    fn entropy(&self) -> Result<Entropy> {
+        Ok(Entropy { value: solana_program::hash::hash(&self.seed.to_le_bytes()).to_bytes(), slot: 0, signature: vec![] })
+    }
+}
+
+// Ed25519 precompile data: u8 count, u8 padding, then per signature
+// u16 offsets for (signature, sig ix, pubkey, pubkey ix, message, message size, message ix)
+// Returns (signature, pubkey, message).
+pub fn fn_ed25519_single(data: &[u8]) -> Result<(&[u8], &[u8], &[u8])> {
+    require!(data.len() >= 16 && data[0] == 1, SpinError::InvalidOracleSignature);
+
+    let read_u16 = |at: usize| u16::from_le_bytes([data[at], data[at + 1]]) as usize;
+    let signature_offset = read_u16(2);
+    let signature_ix = read_u16(4);
+    let pubkey_offset = read_u16(6);
+    let pubkey_ix = read_u16(8);
+    let message_offset = read_u16(10);
+    let message_size = read_u16(12);
+    let message_ix = read_u16(14);
+
+    // u16::MAX means "this instruction"; anything else could point at unsigned data
+    let this_ix = u16::MAX as usize;
+    require!(
+        signature_ix == this_ix && pubkey_ix == this_ix && message_ix == this_ix,
+        SpinError::InvalidOracleSignature
+    );
+    require!(
+        data.len() >= signature_offset + 64
+            && data.len() >= pubkey_offset + 32
+            && data.len() >= message_offset + message_size,
+        SpinError::InvalidOracleSignature
+    );
+
+    Ok((
+        &data[signature_offset..signature_offset + 64],
+        &data[pubkey_offset..pubkey_offset + 32],
+        &data[message_offset..message_offset + message_size],
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // SlotHashes entries, newest first, each hashing to [slot as u8; 32]
+    fn entries(slots: &[u64]) -> Vec<u8> {
+        slots.iter().flat_map(|slot| [slot.to_le_bytes().to_vec(), vec![*slot as u8; 32]].concat()).collect()
+    }
+
+    #[test]
+    fn slot_hash_exact_slot() {
+        let data = entries(&[105, 104, 103, 102]);
+        let entropy = fn_oldest_slot_hash(&data, 4, 103).unwrap();
+        assert_eq!((entropy.slot, entropy.value), (103, [103; 32]));
+    }
+
+    #[test]
+    fn slot_hash_skipped_slot_uses_next() {
+        // 103 and 104 were skipped
+        let data = entries(&[107, 106, 105, 102]);
+        let entropy = fn_oldest_slot_hash(&data, 4, 103).unwrap();
+        assert_eq!((entropy.slot, entropy.value), (105, [105; 32]));
+    }
+
+    #[test]
+    fn slot_hash_needs_proof_of_oldest() {
+        // nothing older than 103 is listed, so 105 may not be the first entry after it
+        let data = entries(&[107, 106, 105]);
+        assert!(fn_oldest_slot_hash(&data, 3, 103).is_none());
+        // and nothing at or after it yet
+        let data = entries(&[102, 101]);
+        assert!(fn_oldest_slot_hash(&data, 2, 103).is_none());
+    }
+
+    #[test]
+    fn ed25519_single_returns_signature() {
+        // header, then signature at 16, pubkey at 80, 32-byte message at 112
+        let mut data = vec![1, 0];
+        for offset in [16u16, u16::MAX, 80, u16::MAX, 112, 32, u16::MAX] {
+            data.extend_from_slice(&offset.to_le_bytes());
+        }
+        data.extend_from_slice(&[7; 64]);
+        data.extend_from_slice(&[8; 32]);
+        data.extend_from_slice(&[9; 32]);
+
+        let (signature, pubkey, message) = fn_ed25519_single(&data).unwrap();
+        assert_eq!((signature, pubkey, message), (&[7u8; 64][..], &[8u8; 32][..], &[9u8; 32][..]));
+
+        // a signature running past the data is rejected
+        data[2..4].copy_from_slice(&100u16.to_le_bytes());
+        assert!(fn_ed25519_single(&data).is_err());
+    }
+}
@@ -0,0 +1,271 @@
+use anchor_lang::prelude::*;
+use solana_program::hash::hashv;
+
+// Everything needed to recompute a spin off-chain. settle_spin builds one,
+// derives the winning index through verify_spin and emits it in SpinSettled.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default, Debug, PartialEq)]
+pub struct SpinReceipt {
+    pub pool: Pubkey,
+    pub user: Pubkey,
+    pub round_num: u32,
+    // position within a spin_many batch, 0 for single spins
+    pub draw_index: u8,
+
+    // seed material; hash(secret) must equal the commitment made at request time
+    pub randomness_source: u8,
+    pub commitment: [u8; 32],
+    pub secret: [u8; 32],
+    pub entropy: [u8; 32],
+
+    // where `entropy` came from. SlotHashes spins use the hash of entropy_slot,
+    // the first slot at or after reveal_slot. Oracle spins use hash(oracle_signature),
+    // `oracle`'s ed25519 signature over the pending account address `request`.
+    pub reveal_slot: u64,
+    pub entropy_slot: u64,
+    pub request: Pubkey,
+    pub oracle: Pubkey,
+    pub oracle_signature: Vec<u8>,
+
+    // configured ratios, and the subset the vaults could pay when the spin was requested
+    pub ratio_list: Vec<u32>,
+    pub weights: Vec<u32>,
+    // set when the item `weights` picked could no longer be paid at settle time:
+    // the weights still payable then, rolled with the same seed. Empty otherwise.
+    pub redraw_weights: Vec<u32>,
+
+    pub last_spinindex: u8,
+}
+
+pub fn fn_spin_seed(secret: &[u8; 32], entropy: &[u8; 32], user: &Pubkey, round_num: u32) -> [u8; 32] {
+    hashv(&[secret, entropy, user.as_ref(), &round_num.to_le_bytes()]).to_bytes()
+}
+
+// later draws of a batch rehash the spin seed with their index, so draw 0
+// rolls exactly like a single spin
+pub fn fn_draw_seed(seed: &[u8; 32], draw_index: u8) -> [u8; 32] {
+    if draw_index == 0 {
+        *seed
+    } else {
+        hashv(&[seed, &[draw_index]]).to_bytes()
+    }
+}
+
+// largest accepted draw for `bound`: 0..=zone holds a whole number of
+// 0..bound cycles, everything above is the partial bucket
+pub fn fn_rejection_zone(bound: u64) -> u64 {
+    u64::MAX - (u64::MAX - bound + 1) % bound
+}
+
+// uniform value in 0..bound; draws from the top partial bucket of u64 are
+// rejected and redrawn from hash(seed, counter) so no value is favoured
+pub fn fn_uniform(seed: &[u8; 32], bound: u64) -> u64 {
+    let zone = fn_rejection_zone(bound);
+    let mut block = *seed;
+    let mut counter: u32 = 0;
+    loop {
+        let x = u64::from_le_bytes(block[..8].try_into().unwrap());
+        if x <= zone {
+            return x % bound;
+        }
+        counter += 1;
+        block = hashv(&[seed, &counter.to_le_bytes()]).to_bytes();
+    }
+}
+
+// picks an index with probability weight / sum(weights)
+pub fn fn_spin_roll(weights: &[u32], seed: &[u8; 32]) -> Option<u8> {
+    let total: u64 = weights.iter().map(|w| *w as u64).sum();
+    if total == 0 {
+        return None;
+    }
+
+    let r = fn_uniform(seed, total);
+    let mut start = 0;
+    for (pos, weight) in weights.iter().enumerate() {
+        let end = start + *weight as u64;
+        if r < end {
+            return Some(pos as u8);
+        }
+        start = end;
+    }
+    None
+}
+
+// Recomputes the winning index from the receipt inputs, ignoring receipt.last_spinindex.
+// None when no item was affordable, or when a redraw left in the item it replaced.
+pub fn verify_spin(receipt: &SpinReceipt) -> Option<u8> {
+    let seed = fn_spin_seed(&receipt.secret, &receipt.entropy, &receipt.user, receipt.round_num);
+    let draw_seed = fn_draw_seed(&seed, receipt.draw_index);
+    let index = fn_spin_roll(&receipt.weights, &draw_seed)?;
+    if receipt.redraw_weights.is_empty() {
+        return Some(index);
+    }
+    if receipt.redraw_weights.get(index as usize).copied().unwrap_or(0) != 0 {
+        return None;
+    }
+    fn_spin_roll(&receipt.redraw_weights, &draw_seed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn receipt() -> SpinReceipt {
+        SpinReceipt {
+            pool: Pubkey::new_from_array([1; 32]),
+            user: Pubkey::new_from_array([2; 32]),
+            round_num: 7,
+            draw_index: 0,
+            randomness_source: 0,
+            commitment: [3; 32],
+            secret: [4; 32],
+            entropy: [5; 32],
+            reveal_slot: 100,
+            entropy_slot: 100,
+            request: Pubkey::new_from_array([6; 32]),
+            oracle: Pubkey::default(),
+            oracle_signature: vec![],
+            ratio_list: vec![50_000, 30_000, 19_990, 10],
+            weights: vec![50_000, 30_000, 19_990, 10],
+            redraw_weights: vec![],
+            last_spinindex: 0,
+        }
+    }
+
+    #[test]
+    fn draw_seed_keeps_first_draw() {
+        let seed = fn_spin_seed(&[4; 32], &[5; 32], &Pubkey::new_from_array([2; 32]), 7);
+        assert_eq!(fn_draw_seed(&seed, 0), seed);
+        assert_ne!(fn_draw_seed(&seed, 1), seed);
+        assert_ne!(fn_draw_seed(&seed, 1), fn_draw_seed(&seed, 2));
+    }
+
+    // winning index of receipt() for draw_index 0..8, pinned so a change to the
+    // seed or sampling code can't silently change past results
+    const KNOWN_DRAWS: [u8; 8] = [0, 0, 0, 0, 0, 1, 2, 2];
+
+    #[test]
+    fn verify_spin_matches_known_receipt() {
+        for (draw_index, known) in KNOWN_DRAWS.iter().enumerate() {
+            let mut receipt = SpinReceipt { draw_index: draw_index as u8, ..receipt() };
+            assert_eq!(verify_spin(&receipt), Some(*known));
+
+            // the recorded index is an output, not an input
+            receipt.last_spinindex = 3;
+            assert_eq!(verify_spin(&receipt), Some(*known));
+        }
+    }
+
+    fn seed_with(first: u64, tag: u8) -> [u8; 32] {
+        let mut seed = [tag; 32];
+        seed[..8].copy_from_slice(&first.to_le_bytes());
+        seed
+    }
+
+    #[test]
+    fn uniform_bound_one() {
+        for tag in 0..=255 {
+            assert_eq!(fn_uniform(&[tag; 32], 1), 0);
+        }
+        assert_eq!(fn_uniform(&seed_with(u64::MAX, 0), 1), 0);
+    }
+
+    #[test]
+    fn uniform_jackpot_bound() {
+        // DEFAULT_RATIO_TOTAL, where one unit of ratio is 0.001%
+        let bound = 100_000;
+        assert_eq!(fn_uniform(&seed_with(123_456_789, 0), bound), 56_789);
+        assert_eq!(fn_uniform(&seed_with(99_999, 0), bound), 99_999);
+        assert_eq!(fn_uniform(&seed_with(100_000, 0), bound), 0);
+        for tag in 0..=255 {
+            assert!(fn_uniform(&[tag; 32], bound) < bound);
+        }
+    }
+
+    #[test]
+    fn rejection_zone_near_u64_max() {
+        let half = 1u64 << 63;
+        for bound in [1, 2, 3, 100_000, half - 1, half, half + 1, u64::MAX - 1, u64::MAX] {
+            let zone = fn_rejection_zone(bound);
+            // accepted values cover whole cycles of the bound, and the rest is less than one
+            assert_eq!((zone as u128 + 1) % bound as u128, 0, "bound {}", bound);
+            assert!(u64::MAX as u128 - (zone as u128) < bound as u128, "bound {}", bound);
+        }
+        assert_eq!(fn_rejection_zone(half + 1), half);
+        assert_eq!(fn_rejection_zone(u64::MAX), u64::MAX - 1);
+        assert_eq!(fn_rejection_zone(half), u64::MAX);
+
+        // a draw in the partial bucket is redrawn instead of wrapping to a low value
+        let bound = half + 1;
+        let seed = seed_with(u64::MAX, 9);
+        let value = fn_uniform(&seed, bound);
+        assert!(value < bound);
+        assert_ne!(value, u64::MAX % bound);
+    }
+
+    #[test]
+    fn roll_hits_jackpot_slot() {
+        // a 0.01% jackpot is 10 units of DEFAULT_RATIO_TOTAL, the last ten values of 0..100_000
+        let weights = [99_990, 10];
+        assert_eq!(fn_spin_roll(&weights, &seed_with(99_989, 0)), Some(0));
+        for value in 99_990..100_000 {
+            assert_eq!(fn_spin_roll(&weights, &seed_with(value, 0)), Some(1));
+            assert_eq!(fn_spin_roll(&weights, &seed_with(value + 7 * 100_000, 0)), Some(1));
+        }
+        assert_eq!(fn_spin_roll(&weights, &seed_with(100_000, 0)), Some(0));
+    }
+
+    #[test]
+    fn roll_skips_zero_weights() {
+        let weights = [0, 5, 0, 3, 0];
+        for tag in 0..=255 {
+            let index = fn_spin_roll(&weights, &[tag; 32]).unwrap();
+            assert!(index == 1 || index == 3);
+        }
+        assert_eq!(fn_spin_roll(&[0, 0, 1], &[7; 32]), Some(2));
+    }
+
+    #[test]
+    fn roll_without_weight() {
+        assert_eq!(fn_spin_roll(&[], &[7; 32]), None);
+        assert_eq!(fn_spin_roll(&[0, 0, 0], &[7; 32]), None);
+    }
+
+    #[test]
+    fn verify_spin_follows_inputs() {
+        let base = receipt();
+        let seed = fn_spin_seed(&base.secret, &base.entropy, &base.user, base.round_num);
+        for draw_index in 0..20 {
+            let receipt = SpinReceipt { draw_index, ..base.clone() };
+            assert_eq!(verify_spin(&receipt), fn_spin_roll(&receipt.weights, &fn_draw_seed(&seed, draw_index)));
+        }
+
+        let receipt = SpinReceipt { weights: vec![0, 0, 1, 0], ..base.clone() };
+        assert_eq!(verify_spin(&receipt), Some(2));
+
+        let receipt = SpinReceipt { weights: vec![0; 4], ..base };
+        assert_eq!(verify_spin(&receipt), None);
+    }
+
+    #[test]
+    fn verify_spin_redraw() {
+        // draw 0 of the fixture lands on item 0
+        let base = receipt();
+        assert_eq!(verify_spin(&base), Some(0));
+
+        let seed = fn_spin_seed(&base.secret, &base.entropy, &base.user, base.round_num);
+        let redraw_weights = vec![0, 30_000, 19_990, 10];
+        let redrawn = SpinReceipt { redraw_weights: redraw_weights.clone(), ..base.clone() };
+        let index = verify_spin(&redrawn).unwrap();
+        assert_ne!(index, 0);
+        assert_eq!(Some(index), fn_spin_roll(&redraw_weights, &seed));
+
+        // a redraw that still holds the item it replaced isn't one
+        let kept = SpinReceipt { redraw_weights: vec![1, 30_000, 19_990, 10], ..base.clone() };
+        assert_eq!(verify_spin(&kept), None);
+
+        // nothing left to redraw
+        let empty = SpinReceipt { redraw_weights: vec![0; 4], ..base };
+        assert_eq!(verify_spin(&empty), None);
+    }
+}
@@ -1,16 +1,224 @@
 use crate::{account::*};
+use crate::errors::*;
 use anchor_lang::prelude::*;
+use anchor_spl::metadata::{mpl_token_metadata, MetadataAccount};
+use anchor_spl::token_2022::spl_token_2022;
+use anchor_spl::token_2022::spl_token_2022::extension::{transfer_fee::TransferFeeConfig, BaseStateWithExtensions, StateWithExtensions};
+use anchor_spl::token_interface::{Mint, TokenAccount};
+use solana_program::{program::invoke, system_instruction};
 
 use crate::constants::*;
 
-pub fn fn_dev_fee(pool: &Pool, amount: u64) -> Result<u64> {
-    let fee = pool.dev_fee as u128;
-    let res = (amount as u128) * fee / 100 / PERCENT_MULTIPLIER as u128;
-    Ok(res as u64)
+pub fn fn_fee_share(amount: u64, bps: u16) -> u64 {
+    ((amount as u128) * bps as u128 / BPS_DENOMINATOR as u128) as u64
 }
 
-pub fn fn_burn_fee(pool: &Pool, amount: u64) -> Result<u64> {
-    let fee = pool.burn_fee as u128;
-    let res = (amount as u128) * fee / 100 / PERCENT_MULTIPLIER as u128;
-    Ok(res as u64)
+
+pub fn fn_reward_vault_address(program_id: &Pubkey, pool: &Pubkey, mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[REWARD_VAULT_SEED, pool.as_ref(), mint.as_ref()], program_id).0
+}
+
+// the pool's paid item table or its free-spin table
+pub fn fn_is_item_table(table: &Pubkey, pool: &Pubkey) -> bool {
+    [ITEM_LIST_SEED, FREE_ITEM_LIST_SEED]
+        .iter()
+        .any(|seed| Pubkey::find_program_address(&[seed, pool.as_ref()], &crate::ID).0 == *table)
+}
+
+// balances of the pool's reward vaults passed in remaining_accounts, keyed by mint.
+// Only the pool's reward vault PDA counts, so a caller can't pass an
+// empty lookalike vault to knock an item out of the draw.
+pub fn fn_reward_vault_balances(program_id: &Pubkey, pool: &Pubkey, accounts: &[AccountInfo]) -> Result<Vec<(Pubkey, u64)>> {
+    let mut balances = Vec::with_capacity(accounts.len());
+    for info in accounts {
+        require!(
+            *info.owner == anchor_spl::token::ID || *info.owner == spl_token_2022::ID,
+            SpinError::InvalidRewardVault
+        );
+        let vault = TokenAccount::try_deserialize(&mut &info.try_borrow_data()?[..])?;
+        require!(
+            info.key() == fn_reward_vault_address(program_id, pool, &vault.mint),
+            SpinError::InvalidRewardVault
+        );
+        balances.push((vault.mint, vault.amount));
+    }
+    Ok(balances)
+}
+
+// what the vaults can still pay out: the SOL vault above rent and the reward
+// vaults in `accounts`, each less whatever earlier winners haven't claimed yet
+pub fn fn_free_balances(program_id: &Pubkey, pool: &Account<Pool>, vault: &AccountInfo, accounts: &[AccountInfo]) -> Result<(u64, Vec<(Pubkey, u64)>)> {
+    let sol_balance = vault.lamports()
+        .saturating_sub(Rent::get()?.minimum_balance(0))
+        .saturating_sub(pool.liability(&NATIVE_SOL_MINT));
+    let token_balances = fn_reward_vault_balances(program_id, &pool.key(), accounts)?
+        .into_iter()
+        .map(|(mint, balance)| (mint, balance.saturating_sub(pool.liability(&mint))))
+        .collect();
+    Ok((sol_balance, token_balances))
+}
+
+// Fixes the items a new spin of `spin_count` draws can land on, before its entropy
+// exists. `accounts` start with the pool's reward vault of every mint in
+// SpinItemList::prize_mints; returns the affordable mask, what the spin must
+// reserve for it and how many of the accounts were vaults.
+pub fn fn_snapshot_draw(
+    program_id: &Pubkey,
+    pool: &Account<Pool>,
+    table: &AccountInfo,
+    vault: &AccountInfo,
+    accounts: &[AccountInfo],
+    spin_count: u8,
+) -> Result<([u8; 32], Vec<Liability>, usize)> {
+    let data = table.try_borrow_data()?;
+    let (state, items) = SpinItemList::from_data(&data);
+    require!(state.ratio_sum(items) == pool.ratio_total, SpinError::InvalidRatioTable);
+
+    let vault_count = state.prize_mints(items, spin_count)?.len();
+    require!(accounts.len() >= vault_count, SpinError::MissingRewardVault);
+    let (sol_balance, token_balances) = fn_free_balances(program_id, pool, vault, &accounts[..vault_count])?;
+
+    let (mask, reservation) = state.affordable_mask(items, sol_balance, &token_balances, spin_count)?;
+    require!(mask != [0; 32], SpinError::NoAffordableItem);
+    Ok((mask, reservation, vault_count))
+}
+
+// grows `account` to `len` bytes when it is smaller, `payer` topping up its rent
+pub fn fn_grow_account<'info>(
+    account: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    len: usize,
+) -> Result<()> {
+    if account.data_len() >= len {
+        return Ok(());
+    }
+    let rent = Rent::get()?.minimum_balance(len).saturating_sub(account.lamports());
+    if rent > 0 {
+        invoke(
+            &system_instruction::transfer(payer.key, account.key, rent),
+            &[payer.clone(), account.clone(), system_program.clone()],
+        )?;
+    }
+    account.realloc(len, false)?;
+    Ok(())
+}
+
+// token account owned by `token_program`, read from an unchecked account
+pub fn fn_token_account(info: &AccountInfo, token_program: &Pubkey) -> Result<TokenAccount> {
+    require!(info.owner == token_program, SpinError::InvalidRewardVault);
+    TokenAccount::try_deserialize(&mut &info.try_borrow_data()?[..])
+}
+
+// fills reward_mints.decimals_list from the mint accounts passed in remaining_accounts,
+// so item amounts in whole tokens can be scaled to base units at settle time
+pub fn fn_reward_decimals(reward_mints: &mut ItemRewardMints, accounts: &[AccountInfo]) -> Result<()> {
+    require!(reward_mints.count as usize <= REWARD_TOKEN_COUNT_PER_ITEM, SpinError::CountOverflowAddItem);
+    for i in 0..reward_mints.count as usize {
+        let info = accounts
+            .iter()
+            .find(|info| info.key() == reward_mints.item_mint_list[i])
+            .ok_or(SpinError::MissingRewardMint)?;
+        require!(
+            *info.owner == anchor_spl::token::ID || *info.owner == spl_token_2022::ID,
+            SpinError::MissingRewardMint
+        );
+        let mint = Mint::try_deserialize(&mut &info.try_borrow_data()?[..])?;
+        reward_mints.decimals_list[i] = mint.decimals;
+    }
+    Ok(())
+}
+
+// a default collection accepts any NFT; otherwise `metadata` must be the mint's
+// Metaplex metadata and carry that collection as verified
+pub fn fn_check_collection(mint: &Pubkey, collection: &Pubkey, metadata: Option<&AccountInfo>) -> Result<()> {
+    if *collection == Pubkey::default() {
+        return Ok(());
+    }
+
+    let metadata = metadata.ok_or(SpinError::InvalidNftCollection)?;
+    require!(
+        *metadata.owner == mpl_token_metadata::ID
+            && metadata.key() == mpl_token_metadata::accounts::Metadata::find_pda(mint).0,
+        SpinError::InvalidNftCollection
+    );
+
+    let metadata = MetadataAccount::try_deserialize(&mut &metadata.try_borrow_data()?[..])?;
+    match &metadata.collection {
+        Some(c) if c.verified && c.key == *collection => Ok(()),
+        _ => err!(SpinError::InvalidNftCollection),
+    }
+}
+
+// fee withheld by a Token-2022 transfer-fee mint when moving `amount`, 0 for every other mint
+pub fn fn_transfer_fee(mint: &AccountInfo, amount: u64) -> Result<u64> {
+    if *mint.owner != spl_token_2022::ID {
+        return Ok(0);
+    }
+
+    let data = mint.try_borrow_data()?;
+    let mint = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&data)?;
+    match mint.get_extension::<TransferFeeConfig>() {
+        Ok(config) => Ok(config
+            .calculate_epoch_fee(Clock::get()?.epoch, amount)
+            .ok_or(ProgramError::ArithmeticOverflow)?),
+        Err(_) => Ok(0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use std::str::FromStr;
+
+    // the account loaded into the test validator, see Anchor.toml
+    const FIXTURE: &str = include_str!("../../../tests/fixtures/nft_metadata.json");
+    const MINT: &str = "k7FaK87WHGVXzkaoHb7CdVPgkKDQhZ29VLDeBVbDfYn";
+    const COLLECTION: &str = "p2Yicb86aZig616Eav2VWG9vuXR5mEqhtzshZYBxzsV";
+
+    fn fixture_data() -> Vec<u8> {
+        let data = FIXTURE.split("\"data\": [").nth(1).unwrap();
+        let encoded = data.split('"').nth(1).unwrap();
+        STANDARD.decode(encoded).unwrap()
+    }
+
+    fn check(mint: &str, collection: &str, key: Pubkey, owner: Pubkey, mut data: Vec<u8>) -> Result<()> {
+        let mut lamports = 0;
+        let metadata = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &owner, false, 0);
+        fn_check_collection(&Pubkey::from_str(mint).unwrap(), &Pubkey::from_str(collection).unwrap(), Some(&metadata))
+    }
+
+    fn fixture_key() -> Pubkey {
+        Pubkey::from_str(FIXTURE.split('"').nth(3).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn collection_fixture_is_verified() {
+        check(MINT, COLLECTION, fixture_key(), mpl_token_metadata::ID, fixture_data()).unwrap();
+    }
+
+    #[test]
+    fn collection_mismatch_is_rejected() {
+        let invalid = Some(SpinError::InvalidNftCollection.into());
+        let other = Pubkey::new_unique().to_string();
+
+        // another collection, or the fixture passed for another mint
+        assert_eq!(check(MINT, &other, fixture_key(), mpl_token_metadata::ID, fixture_data()).err(), invalid);
+        assert_eq!(check(&other, COLLECTION, fixture_key(), mpl_token_metadata::ID, fixture_data()).err(), invalid);
+        // not owned by the metadata program
+        assert_eq!(check(MINT, COLLECTION, fixture_key(), Pubkey::new_unique(), fixture_data()).err(), invalid);
+
+        // collection set but not verified
+        let mut data = fixture_data();
+        let verified_at = data.windows(33).position(|w| w[0] == 1 && w[1..] == Pubkey::from_str(COLLECTION).unwrap().to_bytes()).unwrap();
+        data[verified_at] = 0;
+        assert_eq!(check(MINT, COLLECTION, fixture_key(), mpl_token_metadata::ID, data).err(), invalid);
+    }
+
+    #[test]
+    fn default_collection_needs_no_metadata() {
+        fn_check_collection(&Pubkey::new_unique(), &Pubkey::default(), None).unwrap();
+        assert!(fn_check_collection(&Pubkey::new_unique(), &Pubkey::new_unique(), None).is_err());
+    }
 }
@@ -0,0 +1,58 @@
+use anchor_lang::prelude::*;
+use solana_program::ed25519_program;
+use solana_program::sysvar::instructions::{load_current_index_checked, load_instruction_at_checked};
+
+use crate::account::*;
+use crate::constants::*;
+use crate::errors::*;
+use crate::randomness::fn_ed25519_single;
+
+// Promo spin signed off-chain by one of the pool's voucher issuers. The
+// instruction right before the spin must verify the issuer's signature over
+// the Borsh encoding of the whole voucher through the ed25519 precompile.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default)]
+pub struct Voucher {
+    pub issuer: Pubkey,
+    pub user: Pubkey,
+    pub pool: Pubkey,
+    // off the spin price, BPS_DENOMINATOR for a free spin
+    pub discount_bps: u16,
+    // spins the discount applies to, a spin_many batch pays full price for the rest
+    pub spin_count: u8,
+    // unix time
+    pub expires_at: i64,
+    // one redemption per (issuer, nonce), see UsedVoucher
+    pub nonce: u64,
+}
+
+impl Voucher {
+    pub fn verify(&self, instructions: &AccountInfo, pool: &Account<Pool>, user: &Pubkey) -> Result<()> {
+        require!(pool.voucher_issuers().contains(&self.issuer), SpinError::InvalidVoucher);
+        require!(self.user == *user && self.pool == pool.key(), SpinError::InvalidVoucher);
+        require!(self.discount_bps as u64 <= BPS_DENOMINATOR && self.spin_count > 0, SpinError::InvalidVoucher);
+        require!(Clock::get()?.unix_timestamp <= self.expires_at, SpinError::VoucherExpired);
+
+        let current = load_current_index_checked(instructions)?;
+        require!(current > 0, SpinError::InvalidVoucher);
+
+        let ix = load_instruction_at_checked(current as usize - 1, instructions)?;
+        require!(ix.program_id == ed25519_program::ID, SpinError::InvalidVoucher);
+
+        let (_, pubkey, message) = fn_ed25519_single(&ix.data).map_err(|_| SpinError::InvalidVoucher)?;
+        require!(
+            pubkey == self.issuer.as_ref() && message == self.try_to_vec()?.as_slice(),
+            SpinError::InvalidVoucher
+        );
+
+        Ok(())
+    }
+}
+
+// seeds of the UsedVoucher record, default when the spin has no voucher
+pub fn fn_voucher_issuer(voucher: &Option<Voucher>) -> Pubkey {
+    voucher.as_ref().map(|v| v.issuer).unwrap_or_default()
+}
+
+pub fn fn_voucher_nonce(voucher: &Option<Voucher>) -> u64 {
+    voucher.as_ref().map_or(0, |v| v.nonce)
+}
@@ -10,7 +10,7 @@ describe("spin_game", () => {
 
   it("Is initialized!", async () => {
     // Add your test here.
-    const tx = await program.methods.initialize().rpc();
+    const tx = await program.methods.initialize(new anchor.BN(0)).rpc();
     console.log("Your transaction signature", tx);
   });
 });
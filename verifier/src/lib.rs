@@ -0,0 +1,164 @@
+use anchor_lang::solana_program::hash::hash;
+use anchor_lang::{AnchorDeserialize, Discriminator};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use ed25519_dalek::{PublicKey, Signature};
+
+use spin_game::constants::{RANDOMNESS_SOURCE_ORACLE, RANDOMNESS_SOURCE_SLOT_HASHES};
+
+pub use spin_game::events::SpinSettled;
+pub use spin_game::receipt::{verify_spin, SpinReceipt};
+
+const PROGRAM_DATA_PREFIX: &str = "Program data: ";
+
+// Decodes a SpinSettled receipt from one transaction log line, if it carries one.
+pub fn parse_receipt(log: &str) -> Option<SpinReceipt> {
+    let encoded = log.trim().strip_prefix(PROGRAM_DATA_PREFIX)?;
+    let data = STANDARD.decode(encoded).ok()?;
+    if data.len() < 8 || data[..8] != SpinSettled::DISCRIMINATOR {
+        return None;
+    }
+    let event = SpinSettled::try_from_slice(&data[8..]).ok()?;
+    Some(event.receipt)
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ReceiptError {
+    // hash(secret) is not the commitment made when the spin was requested
+    CommitmentMismatch,
+    // entropy doesn't follow from its recorded source
+    EntropyMismatch,
+    // the inputs give another index, None when nothing was affordable
+    IndexMismatch(Option<u8>),
+}
+
+// Checks the revealed secret and where the entropy came from, then recomputes
+// the winning index and compares it with the one the program recorded.
+// A SlotHashes receipt can only be checked for slot order here; compare
+// entropy with the hash of entropy_slot on any RPC node.
+pub fn check_receipt(receipt: &SpinReceipt) -> Result<u8, ReceiptError> {
+    if hash(&receipt.secret).to_bytes() != receipt.commitment {
+        return Err(ReceiptError::CommitmentMismatch);
+    }
+    if !check_entropy(receipt) {
+        return Err(ReceiptError::EntropyMismatch);
+    }
+    match verify_spin(receipt) {
+        Some(index) if index == receipt.last_spinindex => Ok(index),
+        other => Err(ReceiptError::IndexMismatch(other)),
+    }
+}
+
+fn check_entropy(receipt: &SpinReceipt) -> bool {
+    match receipt.randomness_source {
+        RANDOMNESS_SOURCE_SLOT_HASHES => receipt.entropy_slot >= receipt.reveal_slot,
+        RANDOMNESS_SOURCE_ORACLE => {
+            let (Ok(oracle), Ok(signature)) =
+                (PublicKey::from_bytes(receipt.oracle.as_ref()), Signature::from_bytes(&receipt.oracle_signature))
+            else {
+                return false;
+            };
+            oracle.verify_strict(receipt.request.as_ref(), &signature).is_ok()
+                && hash(&receipt.oracle_signature).to_bytes() == receipt.entropy
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::prelude::Pubkey;
+    use anchor_lang::Event;
+
+    fn receipt() -> SpinReceipt {
+        SpinReceipt {
+            pool: Pubkey::new_from_array([1; 32]),
+            user: Pubkey::new_from_array([2; 32]),
+            round_num: 7,
+            draw_index: 5,
+            randomness_source: 0,
+            commitment: hash(&[4; 32]).to_bytes(),
+            secret: [4; 32],
+            entropy: [5; 32],
+            reveal_slot: 100,
+            entropy_slot: 101,
+            request: Pubkey::new_from_array([6; 32]),
+            oracle: Pubkey::default(),
+            oracle_signature: vec![],
+            ratio_list: vec![50_000, 30_000, 19_990, 10],
+            weights: vec![50_000, 30_000, 19_990, 10],
+            redraw_weights: vec![],
+            last_spinindex: 1,
+        }
+    }
+
+    fn log_line(receipt: &SpinReceipt) -> String {
+        let event = SpinSettled { receipt: receipt.clone() };
+        format!("{}{}", PROGRAM_DATA_PREFIX, STANDARD.encode(event.data()))
+    }
+
+    #[test]
+    fn parses_program_data_line() {
+        let receipt = receipt();
+        assert_eq!(parse_receipt(&log_line(&receipt)), Some(receipt.clone()));
+        assert_eq!(parse_receipt(&format!("  {}\n", log_line(&receipt))), Some(receipt));
+    }
+
+    #[test]
+    fn skips_other_lines() {
+        assert_eq!(parse_receipt("Program log: last_spinindex: 1"), None);
+        assert_eq!(parse_receipt("Program data: not base64"), None);
+        // some other event
+        assert_eq!(parse_receipt(&format!("{}{}", PROGRAM_DATA_PREFIX, STANDARD.encode([0u8; 16]))), None);
+    }
+
+    #[test]
+    fn checks_recorded_index() {
+        let receipt = parse_receipt(&log_line(&receipt())).unwrap();
+        assert_eq!(check_receipt(&receipt), Ok(1));
+
+        let tampered = SpinReceipt { last_spinindex: 3, ..receipt.clone() };
+        assert_eq!(check_receipt(&tampered), Err(ReceiptError::IndexMismatch(Some(1))));
+
+        let unaffordable = SpinReceipt { weights: vec![0; 4], ..receipt };
+        assert_eq!(check_receipt(&unaffordable), Err(ReceiptError::IndexMismatch(None)));
+    }
+
+    #[test]
+    fn checks_commitment_and_slot() {
+        let receipt = receipt();
+
+        let other_secret = SpinReceipt { secret: [9; 32], ..receipt.clone() };
+        assert_eq!(check_receipt(&other_secret), Err(ReceiptError::CommitmentMismatch));
+
+        let early_slot = SpinReceipt { entropy_slot: 99, ..receipt };
+        assert_eq!(check_receipt(&early_slot), Err(ReceiptError::EntropyMismatch));
+    }
+
+    #[test]
+    fn checks_oracle_signature() {
+        use ed25519_dalek::{Keypair, SecretKey, Signer};
+
+        let secret = SecretKey::from_bytes(&[8; 32]).unwrap();
+        let oracle = Keypair { public: PublicKey::from(&secret), secret };
+        let base = receipt();
+        let signature = oracle.sign(base.request.as_ref()).to_bytes().to_vec();
+        let mut signed = SpinReceipt {
+            randomness_source: RANDOMNESS_SOURCE_ORACLE,
+            entropy: hash(&signature).to_bytes(),
+            oracle: Pubkey::new_from_array(oracle.public.to_bytes()),
+            oracle_signature: signature,
+            ..base
+        };
+        signed.last_spinindex = verify_spin(&signed).unwrap();
+        assert_eq!(check_receipt(&signed), Ok(signed.last_spinindex));
+
+        // signed by someone else
+        let other = SpinReceipt { oracle: Pubkey::new_from_array([1; 32]), ..signed.clone() };
+        assert_eq!(check_receipt(&other), Err(ReceiptError::EntropyMismatch));
+
+        // entropy not derived from the signature
+        let unhashed = SpinReceipt { entropy: [5; 32], ..signed };
+        assert_eq!(check_receipt(&unhashed), Err(ReceiptError::EntropyMismatch));
+    }
+}
@@ -0,0 +1,48 @@
+use std::io::{self, BufRead};
+use std::process::ExitCode;
+
+use spin_verify::{check_receipt, parse_receipt, ReceiptError};
+
+// Reads transaction logs on stdin (e.g. `solana confirm -v <sig>`) and
+// recomputes every SpinSettled receipt found in them.
+fn main() -> ExitCode {
+    let mut found = 0;
+    let mut mismatched = 0;
+
+    for line in io::stdin().lock().lines().map_while(Result::ok) {
+        let Some(receipt) = parse_receipt(&line) else {
+            continue;
+        };
+        found += 1;
+
+        match check_receipt(&receipt) {
+            Ok(index) => println!(
+                "user {} round {} draw {}: index {} verified",
+                receipt.user, receipt.round_num, receipt.draw_index, index
+            ),
+            Err(ReceiptError::IndexMismatch(index)) => {
+                mismatched += 1;
+                println!(
+                    "user {} round {} draw {}: recorded index {} but inputs give {:?}",
+                    receipt.user, receipt.round_num, receipt.draw_index, receipt.last_spinindex, index
+                );
+            }
+            Err(error) => {
+                mismatched += 1;
+                println!(
+                    "user {} round {} draw {}: {:?}",
+                    receipt.user, receipt.round_num, receipt.draw_index, error
+                );
+            }
+        }
+    }
+
+    if found == 0 {
+        eprintln!("no SpinSettled receipts found in input");
+        return ExitCode::FAILURE;
+    }
+    if mismatched > 0 {
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}